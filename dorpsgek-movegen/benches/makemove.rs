@@ -1,5 +1,6 @@
 use criterion::{criterion_group, criterion_main, Criterion, Throughput};
 use dorpsgek_movegen::{perft, Board, Move, MoveType, Square};
+use tinyvec::ArrayVec;
 
 pub fn makemove_bench(c: &mut Criterion) {
     let startpos =
@@ -123,11 +124,85 @@ pub fn perft_bench(c: &mut Criterion) {
     });
 
     group.finish();
+
+    let board =
+        Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
+            .unwrap();
+
+    let mut group = c.benchmark_group("checks");
+
+    group.sample_size(20);
+
+    group.throughput(Throughput::Elements(9467));
+    group.bench_with_input("3", &board, |b, board| {
+        b.iter(|| {
+            assert_eq!(perft(board, 3), 9467);
+        })
+    });
+
+    group.finish();
+}
+
+pub fn evasion_bench(c: &mut Criterion) {
+    // Position 4 and its mirror: the side to move is in check from a bishop.
+    let white =
+        Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
+            .unwrap();
+    let black =
+        Board::from_fen("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1")
+            .unwrap();
+
+    let mut group = c.benchmark_group("evasions");
+
+    group.sample_size(5_000);
+    group.significance_level(0.005);
+    group.noise_threshold(0.025);
+
+    for (name, board) in [("white", &white), ("black", &black)] {
+        group.throughput(Throughput::Elements(6));
+        group.bench_with_input(name, board, |b, board| {
+            b.iter(|| {
+                let mut moves = ArrayVec::from([Move::default(); 256]);
+                moves.set_len(0);
+                board.generate(&mut moves);
+                moves.len()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+pub fn king_moves_bench(c: &mut Criterion) {
+    // Bare kings facing queens, so most of the moves are king moves tested against the attack table.
+    let quiet = Board::from_fen("8/8/3k4/8/8/3K4/8/q6Q w - - 0 1").unwrap();
+    let check = Board::from_fen("3r4/2k5/8/8/3K4/8/8/7q w - - 0 1").unwrap();
+
+    let mut group = c.benchmark_group("king-moves");
+
+    group.sample_size(5_000);
+    group.significance_level(0.005);
+    group.noise_threshold(0.025);
+
+    for (name, board) in [("quiet", &quiet), ("check", &check)] {
+        group.bench_with_input(name, board, |b, board| {
+            b.iter(|| {
+                let mut moves = ArrayVec::from([Move::default(); 256]);
+                moves.set_len(0);
+                board.generate(&mut moves);
+                moves.len()
+            })
+        });
+    }
+
+    group.finish();
 }
 
 pub fn bench(c: &mut Criterion) {
     makemove_bench(c);
     perft_bench(c);
+    evasion_bench(c);
+    king_moves_bench(c);
 }
 
 criterion_group! {
@@ -1,4 +1,4 @@
-use dorpsgek_movegen::{perft, Board, Move};
+use dorpsgek_movegen::{perft, perft_timed, Board, Move};
 use rayon::prelude::*;
 use tinyvec::ArrayVec;
 
@@ -30,4 +30,7 @@ fn main() {
     let nodes = divide(&startpos, depth);
     println!("size of board: {}", std::mem::size_of::<Board>());
     println!("Perft {}: {}", depth, nodes);
+
+    let (nodes, elapsed, nps) = perft_timed(&startpos, depth);
+    println!("Single-threaded perft {}: {} nodes in {:.3}s ({:.0} nps)", depth, nodes, elapsed.as_secs_f64(), nps);
 }
@@ -0,0 +1,159 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::{Board, CastlingRights, PositionError};
+use crate::{colour::Colour, piece::Piece, square::Square};
+
+/// Construct a `Board` piece by piece, without going through FEN.
+#[derive(Clone)]
+pub struct BoardBuilder {
+    squares: [Option<(Piece, Colour)>; 64],
+    side: Colour,
+    castle: CastlingRights,
+    ep: Option<Square>,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBuilder {
+    /// Create a builder for an empty board with White to move.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            squares: [None; 64],
+            side: Colour::White,
+            castle: CastlingRights::none(),
+            ep: None,
+        }
+    }
+
+    /// Place a piece on a square, replacing whatever was there.
+    #[must_use]
+    pub const fn place(mut self, square: Square, piece: Piece, colour: Colour) -> Self {
+        self.squares[square.into_inner() as usize] = Some((piece, colour));
+        self
+    }
+
+    /// Set the side to move.
+    #[must_use]
+    pub const fn side_to_move(mut self, colour: Colour) -> Self {
+        self.side = colour;
+        self
+    }
+
+    /// Set the castling rights.
+    #[must_use]
+    pub const fn castling(mut self, castle: CastlingRights) -> Self {
+        self.castle = castle;
+        self
+    }
+
+    /// Set the en-passant square.
+    #[must_use]
+    pub const fn en_passant(mut self, ep: Option<Square>) -> Self {
+        self.ep = ep;
+        self
+    }
+
+    /// Build the board, or return `None` if it is not a legal position.
+    #[must_use]
+    pub fn build(&self) -> Option<Board> {
+        self.try_build().ok()
+    }
+
+    /// Build the board, checking it with `Board::validate`.
+    ///
+    /// # Errors
+    /// Returns the first problem found with the position.
+    pub fn try_build(&self) -> Result<Board, PositionError> {
+        let mut b = Board::new();
+
+        for (square, contents) in self.squares.iter().enumerate() {
+            if let Some((piece, colour)) = *contents {
+                // SAFETY: square is always in bounds.
+                #[allow(clippy::cast_possible_truncation)]
+                let square = unsafe { Square::from_u8_unchecked(square as u8) };
+
+                b.data
+                    .add_piece(piece, colour, square, false)
+                    .ok_or(PositionError::TooManyPieces(colour))?;
+            }
+        }
+
+        b.side = self.side;
+        b.castle = self.castle;
+        b.ep = self.ep;
+        b.data.rebuild_attacks();
+        b.drop_uncapturable_ep(b.side);
+        b.update_checkers();
+
+        b.validate()?;
+        Ok(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoardBuilder;
+    use crate::{
+        board::Board,
+        colour::Colour,
+        perft,
+        piece::Piece,
+        square::{File, Rank, Square},
+    };
+
+    #[test]
+    fn king_and_queen_versus_king() {
+        let board = BoardBuilder::new()
+            .place(Square::from_rank_file(Rank::One, File::E), Piece::King, Colour::White)
+            .place(Square::from_rank_file(Rank::One, File::D), Piece::Queen, Colour::White)
+            .place(Square::from_rank_file(Rank::Eight, File::E), Piece::King, Colour::Black)
+            .build()
+            .unwrap();
+
+        assert_eq!(perft(&board, 1), 21);
+        let fen = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(perft(&board, 3), perft(&fen, 3));
+        assert_eq!(board.hash(), fen.hash());
+    }
+
+    #[test]
+    fn rejects_illegal_positions() {
+        let e1 = Square::from_rank_file(Rank::One, File::E);
+        let e8 = Square::from_rank_file(Rank::Eight, File::E);
+        let kings = BoardBuilder::new()
+            .place(e1, Piece::King, Colour::White)
+            .place(e8, Piece::King, Colour::Black);
+
+        assert!(kings.build().is_some());
+        assert!(BoardBuilder::new().place(e1, Piece::King, Colour::White).build().is_none());
+        assert!(kings
+            .clone()
+            .place(Square::from_rank_file(Rank::Eight, File::A), Piece::Pawn, Colour::White)
+            .build()
+            .is_none());
+        assert!(kings
+            .place(Square::from_rank_file(Rank::Four, File::E), Piece::Rook, Colour::White)
+            .build()
+            .is_none());
+    }
+}
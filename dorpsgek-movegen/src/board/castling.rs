@@ -0,0 +1,201 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::Display;
+
+use crate::{
+    colour::Colour,
+    square::{File, Rank, Square},
+};
+
+/// The side of the board a king castles towards.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CastlingSide {
+    /// Castling towards the h-file.
+    King,
+    /// Castling towards the a-file.
+    Queen,
+}
+
+/// The square `colour`'s king starts on, and castles from.
+#[must_use]
+pub const fn king_start(colour: Colour) -> Square {
+    Square::from_rank_file(Rank::One, File::E).relative_to(colour)
+}
+
+/// The square `colour`'s rook starts on, for castling towards `side`.
+#[must_use]
+pub const fn rook_start(colour: Colour, side: CastlingSide) -> Square {
+    let file = match side {
+        CastlingSide::King => File::H,
+        CastlingSide::Queen => File::A,
+    };
+    Square::from_rank_file(Rank::One, file).relative_to(colour)
+}
+
+/// The square `colour`'s king lands on after castling towards `side`.
+#[must_use]
+pub const fn king_castle_dest(colour: Colour, side: CastlingSide) -> Square {
+    let file = match side {
+        CastlingSide::King => File::G,
+        CastlingSide::Queen => File::C,
+    };
+    Square::from_rank_file(Rank::One, file).relative_to(colour)
+}
+
+/// The square `colour`'s rook lands on after castling towards `side`.
+#[must_use]
+pub const fn rook_castle_dest(colour: Colour, side: CastlingSide) -> Square {
+    let file = match side {
+        CastlingSide::King => File::F,
+        CastlingSide::Queen => File::D,
+    };
+    Square::from_rank_file(Rank::One, file).relative_to(colour)
+}
+
+/// Which castling moves each side may still make.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct CastlingRights {
+    white_king: bool,
+    white_queen: bool,
+    black_king: bool,
+    black_queen: bool,
+}
+
+impl Display for CastlingRights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if *self == Self::none() {
+            return write!(f, "-");
+        }
+        for (right, c) in [
+            (self.white_king, 'K'),
+            (self.white_queen, 'Q'),
+            (self.black_king, 'k'),
+            (self.black_queen, 'q'),
+        ] {
+            if right {
+                write!(f, "{c}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CastlingRights {
+    /// No castling rights.
+    #[must_use]
+    pub const fn none() -> Self {
+        Self {
+            white_king: false,
+            white_queen: false,
+            black_king: false,
+            black_queen: false,
+        }
+    }
+
+    /// Every castling right.
+    #[must_use]
+    pub const fn all() -> Self {
+        Self {
+            white_king: true,
+            white_queen: true,
+            black_king: true,
+            black_queen: true,
+        }
+    }
+
+    #[must_use]
+    pub const fn white_king(self) -> bool {
+        self.white_king
+    }
+
+    #[must_use]
+    pub const fn white_queen(self) -> bool {
+        self.white_queen
+    }
+
+    #[must_use]
+    pub const fn black_king(self) -> bool {
+        self.black_king
+    }
+
+    #[must_use]
+    pub const fn black_queen(self) -> bool {
+        self.black_queen
+    }
+
+    /// Returns true if `colour` may castle towards `side`.
+    #[must_use]
+    pub const fn can_castle(self, colour: Colour, side: CastlingSide) -> bool {
+        match (colour, side) {
+            (Colour::White, CastlingSide::King) => self.white_king,
+            (Colour::White, CastlingSide::Queen) => self.white_queen,
+            (Colour::Black, CastlingSide::King) => self.black_king,
+            (Colour::Black, CastlingSide::Queen) => self.black_queen,
+        }
+    }
+
+    const fn right_mut(&mut self, colour: Colour, side: CastlingSide) -> &mut bool {
+        match (colour, side) {
+            (Colour::White, CastlingSide::King) => &mut self.white_king,
+            (Colour::White, CastlingSide::Queen) => &mut self.white_queen,
+            (Colour::Black, CastlingSide::King) => &mut self.black_king,
+            (Colour::Black, CastlingSide::Queen) => &mut self.black_queen,
+        }
+    }
+
+    /// Allow `colour` to castle towards `side`.
+    pub const fn add(&mut self, colour: Colour, side: CastlingSide) {
+        *self.right_mut(colour, side) = true;
+    }
+
+    /// Forbid `colour` from castling towards `side`.
+    pub const fn remove(&mut self, colour: Colour, side: CastlingSide) {
+        *self.right_mut(colour, side) = false;
+    }
+
+    /// The individual rights, in `KQkq` order.
+    #[must_use]
+    pub const fn to_array(self) -> [bool; 4] {
+        [self.white_king, self.white_queen, self.black_king, self.black_queen]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{king_castle_dest, king_start, rook_castle_dest, rook_start, CastlingSide};
+    use crate::{colour::Colour, square::Square};
+
+    #[test]
+    fn castling_squares() {
+        let name = |square: Square| square.to_string();
+        assert_eq!(name(king_start(Colour::White)), "e1");
+        assert_eq!(name(king_start(Colour::Black)), "e8");
+
+        for (colour, side, rook, king_dest, rook_dest) in [
+            (Colour::White, CastlingSide::King, "h1", "g1", "f1"),
+            (Colour::White, CastlingSide::Queen, "a1", "c1", "d1"),
+            (Colour::Black, CastlingSide::King, "h8", "g8", "f8"),
+            (Colour::Black, CastlingSide::Queen, "a8", "c8", "d8"),
+        ] {
+            assert_eq!(name(rook_start(colour, side)), rook);
+            assert_eq!(name(king_castle_dest(colour, side)), king_dest);
+            assert_eq!(name(rook_castle_dest(colour, side)), rook_dest);
+        }
+    }
+}
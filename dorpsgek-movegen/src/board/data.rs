@@ -20,6 +20,7 @@ use super::{
     index::{PieceIndex, PieceIndexArray},
     piecelist::Piecelist,
     piecemask::Piecemask,
+    zobrist,
 };
 use crate::{
     colour::Colour,
@@ -34,6 +35,7 @@ pub struct BoardData {
     piecelist: Piecelist,
     index: PieceIndexArray,
     piecemask: Piecemask,
+    hash: u64,
 }
 
 impl BoardData {
@@ -44,9 +46,15 @@ impl BoardData {
             piecelist: Piecelist::new(),
             index: PieceIndexArray::new(),
             piecemask: Piecemask::new(),
+            hash: 0,
         }
     }
 
+    /// Return the Zobrist hash of the pieces on the board.
+    pub const fn hash(&self) -> u64 {
+        self.hash
+    }
+
     /// Return the piece index on a square, if any.
     pub fn piece_index(&self, square: Square) -> Option<PieceIndex> {
         self.index[square]
@@ -57,6 +65,17 @@ impl BoardData {
         self.bitlist[square] & Bitlist::mask_from_colour(colour)
     }
 
+    /// Return the attacks to a square by white and by black, in that order, from a single table read.
+    pub fn attackers_both(&self, square: Square) -> (Bitlist, Bitlist) {
+        let attacks = self.bitlist[square];
+        (attacks & Bitlist::white(), attacks & Bitlist::black())
+    }
+
+    /// Return the attacks to every square.
+    pub const fn attack_table(&self) -> &BitlistArray {
+        &self.bitlist
+    }
+
     /// Return the square a piece resides on.
     pub fn square_of_piece(&self, bit: PieceIndex) -> Square {
         self.piecelist.get(bit)
@@ -124,16 +143,20 @@ impl BoardData {
         Some(Colour::from(self.index[square]?))
     }
 
-    /// Add a `Piece` to a `Square`.
-    pub fn add_piece(&mut self, piece: Piece, colour: Colour, square: Square, update: bool) {
-        let piece_index = self.piecemask.add_piece(piece, colour);
+    /// Add a `Piece` to a `Square`, returning `None` if `colour` already has 16 pieces.
+    #[must_use]
+    pub fn add_piece(&mut self, piece: Piece, colour: Colour, square: Square, update: bool) -> Option<()> {
+        let piece_index = self.piecemask.add_piece(piece, colour)?;
         self.piecelist.add_piece(piece_index, square);
         self.index.add_piece(piece_index, square);
+        self.hash ^= zobrist::piece(piece, colour, square);
 
         if update {
             self.update_attacks(square, piece_index, piece, true, None);
             self.update_sliders(square, false);
         }
+
+        Some(())
     }
 
     /// Remove a piece from a square.
@@ -143,6 +166,7 @@ impl BoardData {
         self.piecemask.remove_piece(piece_index);
         self.piecelist.remove_piece(piece_index, square);
         self.index.remove_piece(piece_index, square);
+        self.hash ^= zobrist::piece(piece, piece_index.colour(), square);
 
         if update {
             self.update_attacks(square, piece_index, piece, false, None);
@@ -171,6 +195,8 @@ impl BoardData {
 
         self.piecelist.move_piece(piece_index, to_square);
         self.index.move_piece(piece_index, from_square, to_square);
+        self.hash ^= zobrist::piece(piece, piece_index.colour(), from_square)
+            ^ zobrist::piece(piece, piece_index.colour(), to_square);
 
         if slide_dir.is_some() {
             self.bitlist.remove_piece(to_square, piece_index);
@@ -254,55 +280,19 @@ impl BoardData {
 
         match piece {
             Piece::Pawn => {
-                if bit.is_white() {
-                    leap(&mut self.bitlist, Direction::NorthEast, square);
-                    leap(&mut self.bitlist, Direction::NorthWest, square);
-                } else {
-                    leap(&mut self.bitlist, Direction::SouthEast, square);
-                    leap(&mut self.bitlist, Direction::SouthWest, square);
+                for &dir in Direction::for_piece(piece) {
+                    leap(&mut self.bitlist, if bit.is_white() { dir } else { dir.opposite() }, square);
                 }
             }
-            Piece::Knight => {
-                leap(&mut self.bitlist, Direction::NorthNorthEast, square);
-                leap(&mut self.bitlist, Direction::EastNorthEast, square);
-                leap(&mut self.bitlist, Direction::EastSouthEast, square);
-                leap(&mut self.bitlist, Direction::SouthSouthEast, square);
-                leap(&mut self.bitlist, Direction::SouthSouthWest, square);
-                leap(&mut self.bitlist, Direction::WestSouthWest, square);
-                leap(&mut self.bitlist, Direction::WestNorthWest, square);
-                leap(&mut self.bitlist, Direction::NorthNorthWest, square);
-            }
-            Piece::King => {
-                leap(&mut self.bitlist, Direction::North, square);
-                leap(&mut self.bitlist, Direction::NorthEast, square);
-                leap(&mut self.bitlist, Direction::East, square);
-                leap(&mut self.bitlist, Direction::SouthEast, square);
-                leap(&mut self.bitlist, Direction::South, square);
-                leap(&mut self.bitlist, Direction::SouthWest, square);
-                leap(&mut self.bitlist, Direction::West, square);
-                leap(&mut self.bitlist, Direction::NorthWest, square);
-            }
-            Piece::Bishop => {
-                slide(&mut self.bitlist, &self.index, Direction::NorthEast, square);
-                slide(&mut self.bitlist, &self.index, Direction::SouthEast, square);
-                slide(&mut self.bitlist, &self.index, Direction::SouthWest, square);
-                slide(&mut self.bitlist, &self.index, Direction::NorthWest, square);
-            }
-            Piece::Rook => {
-                slide(&mut self.bitlist, &self.index, Direction::North, square);
-                slide(&mut self.bitlist, &self.index, Direction::East, square);
-                slide(&mut self.bitlist, &self.index, Direction::South, square);
-                slide(&mut self.bitlist, &self.index, Direction::West, square);
+            Piece::Knight | Piece::King => {
+                for &dir in Direction::for_piece(piece) {
+                    leap(&mut self.bitlist, dir, square);
+                }
             }
-            Piece::Queen => {
-                slide(&mut self.bitlist, &self.index, Direction::North, square);
-                slide(&mut self.bitlist, &self.index, Direction::East, square);
-                slide(&mut self.bitlist, &self.index, Direction::South, square);
-                slide(&mut self.bitlist, &self.index, Direction::West, square);
-                slide(&mut self.bitlist, &self.index, Direction::NorthEast, square);
-                slide(&mut self.bitlist, &self.index, Direction::SouthEast, square);
-                slide(&mut self.bitlist, &self.index, Direction::SouthWest, square);
-                slide(&mut self.bitlist, &self.index, Direction::NorthWest, square);
+            Piece::Bishop | Piece::Rook | Piece::Queen => {
+                for &dir in Direction::for_piece(piece) {
+                    slide(&mut self.bitlist, &self.index, dir, square);
+                }
             }
         }
 
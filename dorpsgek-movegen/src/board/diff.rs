@@ -0,0 +1,179 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::{index::PieceIndex, Board};
+use crate::{
+    chessmove::{Move, MoveType},
+    piece::Piece,
+    square::Square,
+};
+
+/// The changes a move made to the board, for engines mirroring the attack table or piece placement
+/// into their own structures.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MoveDiff {
+    /// Attack table entries added, as the attacked square and the attacking piece.
+    pub attacks_added: Vec<(Square, PieceIndex)>,
+    /// Attack table entries removed, as the attacked square and the attacking piece.
+    pub attacks_removed: Vec<(Square, PieceIndex)>,
+    /// Pieces taken off a square; a piece that moved appears here at its old square.
+    pub pieces_removed: Vec<(PieceIndex, Piece, Square)>,
+    /// Pieces put on a square; a piece that moved appears here at its new square.
+    pub pieces_added: Vec<(PieceIndex, Piece, Square)>,
+}
+
+/// What a move did, for user interfaces and annotators that show captures and checks as moves are played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveInfo {
+    /// The piece the move captured, if any.
+    pub captured: Option<Piece>,
+    /// True if the move put the opponent in check.
+    pub gives_check: bool,
+    /// True if the move checkmated the opponent.
+    pub is_mate: bool,
+}
+
+impl MoveDiff {
+    /// Compare the board before a move with the board after it.
+    fn between(before: &Board, after: &Board) -> Self {
+        let mut diff = Self::default();
+
+        for square in 0_u8..64 {
+            // SAFETY: square is always in bounds.
+            let square = unsafe { Square::from_u8_unchecked(square) };
+            let old = before.data.attack_table()[square];
+            let new = after.data.attack_table()[square];
+
+            diff.attacks_added.extend((new & !old).into_iter().map(|piece| (square, piece)));
+            diff.attacks_removed.extend((old & !new).into_iter().map(|piece| (square, piece)));
+        }
+
+        let placement = |board: &Board, piece| (board.data.piece_from_bit(piece), board.data.square_of_piece(piece));
+        for piece in before.data.pieces() {
+            let (kind, square) = placement(before, piece);
+            if !after.data.pieces().contains(piece.into()) || placement(after, piece) != (kind, square) {
+                diff.pieces_removed.push((piece, kind, square));
+            }
+        }
+        for piece in after.data.pieces() {
+            let (kind, square) = placement(after, piece);
+            if !before.data.pieces().contains(piece.into()) || placement(before, piece) != (kind, square) {
+                diff.pieces_added.push((piece, kind, square));
+            }
+        }
+
+        diff
+    }
+}
+
+impl Board {
+    /// Make a move like `make`, also returning what it changed.
+    /// This costs a scan of the attack table, so plain `make` is better when the diff is not needed.
+    #[must_use]
+    pub fn make_with_diff(&self, m: Move) -> (Self, MoveDiff) {
+        let board = self.make(m);
+        let diff = MoveDiff::between(self, &board);
+        (board, diff)
+    }
+
+    /// Make a move like `make`, also returning whether it captured, checked, or mated.
+    /// Finding mate costs a move generation when the move gives check.
+    #[must_use]
+    pub fn make_info(&self, m: Move) -> (Self, MoveInfo) {
+        let captured = if m.kind == MoveType::EnPassant {
+            Some(Piece::Pawn)
+        } else {
+            self.data.piece_from_square(m.dest)
+        };
+        let board = self.make(m);
+        let gives_check = board.in_check();
+        let is_mate = gives_check && board.legal_move_count() == 0;
+        (board, MoveInfo { captured, gives_check, is_mate })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MoveInfo;
+    use crate::{
+        piece::Piece,
+        test_support::{find_move, legal_moves},
+        Board,
+    };
+
+    #[test]
+    fn diff_reproduces_attack_table() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let moves = legal_moves(&board);
+
+            for m in moves {
+                let (after, diff) = board.make_with_diff(m);
+                assert_eq!(after.hash(), board.make(m).hash());
+
+                let mut attacks = board.data.attack_table().clone();
+                for &(square, piece) in &diff.attacks_removed {
+                    attacks.remove_piece(square, piece);
+                }
+                for &(square, piece) in &diff.attacks_added {
+                    attacks.add_piece(square, piece);
+                }
+                assert_eq!(attacks, *after.data.attack_table(), "{fen} {m}");
+            }
+        }
+    }
+
+    #[test]
+    fn make_info_reports_capture_check_and_mate() {
+        let board = Board::from_fen("6k1/5ppp/8/8/2b5/8/5PPP/3r1RK1 b - - 0 1").unwrap();
+        let find = |uci: &str| find_move(&board, uci);
+
+        // Taking the rook on f1 checks, the bishop stops the king recapturing, and the king has nowhere to go.
+        let (after, info) = board.make_info(find("d1f1"));
+        assert_eq!(info, MoveInfo { captured: Some(Piece::Rook), gives_check: true, is_mate: true });
+        assert_eq!(after.hash(), board.make(find("d1f1")).hash());
+
+        // A quiet rook move does none of these.
+        let (_, info) = board.make_info(find("d1d2"));
+        assert_eq!(info, MoveInfo { captured: None, gives_check: false, is_mate: false });
+    }
+
+    #[test]
+    fn diff_lists_moved_pieces() {
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let find = |uci: &str| {
+            let moves = legal_moves(&board);
+            moves.into_iter().find(|m| m.to_string() == uci).unwrap()
+        };
+
+        // Castling moves two pieces.
+        let (_, diff) = board.make_with_diff(find("e1g1"));
+        let mut removed = diff.pieces_removed.iter().map(|&(_, piece, square)| (square.to_string(), piece)).collect::<Vec<_>>();
+        removed.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(removed, [("e1".to_string(), Piece::King), ("h1".to_string(), Piece::Rook)]);
+        assert_eq!(diff.pieces_added.len(), 2);
+
+        // Promotion swaps a pawn for a queen.
+        let (_, diff) = board.make_with_diff(find("b7b8q"));
+        assert_eq!(diff.pieces_removed.iter().map(|&(_, piece, _)| piece).collect::<Vec<_>>(), [Piece::Pawn]);
+        assert_eq!(diff.pieces_added.iter().map(|&(_, piece, _)| piece).collect::<Vec<_>>(), [Piece::Queen]);
+    }
+}
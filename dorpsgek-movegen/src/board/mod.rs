@@ -16,7 +16,7 @@
  */
 
 use crate::{
-    chessmove::{Move, MoveType},
+    chessmove::{AnnotatedMove, Move, MoveType},
     colour::Colour,
     piece::Piece,
     square::{Direction, File, Rank, Square, Square16x8},
@@ -30,14 +30,27 @@ use std::{
 use tinyvec::ArrayVec;
 
 mod bitlist;
+mod builder;
+mod castling;
 mod data;
+mod diff;
 mod index;
+mod outcome;
 mod piecelist;
 mod piecemask;
+mod san;
+mod see;
+mod validate;
+mod zobrist;
 
 use bitlist::Bitlist;
+pub use builder::BoardBuilder;
+pub use castling::{king_castle_dest, king_start, rook_castle_dest, rook_start, CastlingRights, CastlingSide};
 use data::BoardData;
+pub use diff::{MoveDiff, MoveInfo};
 pub use index::PieceIndex;
+pub use outcome::{DrawReason, Outcome};
+pub use validate::PositionError;
 
 /// Pin information in a board.
 pub struct PinInfo {
@@ -60,6 +73,44 @@ impl Default for PinInfo {
     }
 }
 
+/// Somewhere for the move generator to put the moves it finds.
+trait MoveSink {
+    fn push(&mut self, m: Move);
+}
+
+impl MoveSink for ArrayVec<[Move; 256]> {
+    #[inline]
+    fn push(&mut self, m: Move) {
+        Self::push(self, m);
+    }
+}
+
+/// Counts moves instead of storing them.
+struct MoveCounter(usize);
+
+impl MoveSink for MoveCounter {
+    #[inline]
+    fn push(&mut self, _m: Move) {
+        self.0 += 1;
+    }
+}
+
+/// Passes on only the moves made by the pieces in `pieces`.
+struct PieceFilter<'a, S> {
+    data: &'a BoardData,
+    pieces: Bitlist,
+    inner: &'a mut S,
+}
+
+impl<S: MoveSink> MoveSink for PieceFilter<'_, S> {
+    #[inline]
+    fn push(&mut self, m: Move) {
+        if self.data.piece_index(m.from).is_some_and(|index| self.pieces.contains(Bitlist::from(index))) {
+            self.inner.push(m);
+        }
+    }
+}
+
 /// A chess position.
 #[derive(Clone)]
 pub struct Board {
@@ -68,9 +119,15 @@ pub struct Board {
     /// The side to move.
     side: Colour,
     /// Castling rights, if any.
-    castle: (bool, bool, bool, bool),
+    castle: CastlingRights,
     /// En-passant square, if any.
     ep: Option<Square>,
+    /// Plies since the last irreversible move.
+    halfmove: u32,
+    /// Plies played since the start of the game.
+    ply: u16,
+    /// Enemy pieces giving check to the side to move.
+    checkers: Bitlist,
 }
 
 impl Default for Board {
@@ -79,6 +136,25 @@ impl Default for Board {
     }
 }
 
+/// Boards are serialized as FEN.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+/// Deserializing rejects malformed FEN and positions that fail `Board::validate`, since the input may be untrusted.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        let board = Self::from_fen(&fen).ok_or_else(|| serde::de::Error::custom(format!("invalid FEN {fen:?}")))?;
+        board.validate().map_err(serde::de::Error::custom)?;
+        Ok(board)
+    }
+}
+
 impl Display for Board {
     #[allow(clippy::missing_inline_in_public_items)]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -119,19 +195,7 @@ impl Display for Board {
         } else {
             writeln!(f, "Black to move.")?;
         }
-        if self.castle.0 {
-            write!(f, "K")?;
-        }
-        if self.castle.1 {
-            write!(f, "Q")?;
-        }
-        if self.castle.2 {
-            write!(f, "k")?;
-        }
-        if self.castle.3 {
-            write!(f, "q")?;
-        }
-        writeln!(f)?;
+        writeln!(f, "{}", self.castle)?;
         if let Some(ep) = self.ep {
             writeln!(f, "{}", ep)?;
         } else {
@@ -149,8 +213,11 @@ impl Board {
     pub const fn new() -> Self {
         Self {
             side: Colour::White,
-            castle: (false, false, false, false),
+            castle: CastlingRights::none(),
             ep: None,
+            halfmove: 0,
+            ply: 0,
+            checkers: Bitlist::new(),
             data: BoardData::new(),
         }
     }
@@ -168,23 +235,25 @@ impl Board {
     }
 
     /// Parse a position in Forsyth-Edwards Notation into a board.
+    /// Returns `None` if the FEN is malformed, including if it contains a NUL byte.
     #[must_use]
     pub fn from_fen(fen: &str) -> Option<Self> {
-        let fen = CString::new(fen).expect("FEN is not ASCII");
+        let fen = CString::new(fen).ok()?;
         let fen = fen.as_bytes();
         Self::from_fen_bytes(fen)
     }
 
     /// Parse a position in Forsyth-Edwards Notation into a board.
-    ///
-    /// # Panics
-    /// Panics when invalid FEN is input.
+    /// Surrounding whitespace, such as the line ending of an EPD file, is ignored.
+    /// Returns `None` if the FEN is malformed or a side has more than the 16 pieces a board can hold.
+    /// The position itself isn't checked; use `validate` for that.
     #[must_use]
     pub fn from_fen_bytes(fen: &[u8]) -> Option<Self> {
+        let fen = fen.trim_ascii();
         let mut b = Self::new();
 
         let mut idx = 0_usize;
-        let mut c = fen[idx];
+        let mut c = *fen.first()?;
 
         for rank in (0..=7).rev() {
             let mut file = 0;
@@ -216,68 +285,197 @@ impl Board {
                     let square =
                         Square::from_rank_file(rank.try_into().unwrap(), file.try_into().unwrap());
 
-                    b.data.add_piece(piece, colour, square, false);
+                    b.data.add_piece(piece, colour, square, false)?;
 
                     file += 1;
                 }
                 idx += 1;
-                c = fen[idx];
+                c = *fen.get(idx)?;
             }
             if rank > 0 {
                 idx += 1;
-                c = fen[idx];
+                c = *fen.get(idx)?;
             }
         }
         idx += 1;
-        c = fen[idx];
+        c = *fen.get(idx)?;
         b.side = match c {
             b'w' => Colour::White,
             b'b' => Colour::Black,
             _ => return None,
         };
         idx += 2;
-        c = fen[idx];
-        b.castle = (false, false, false, false);
+        c = *fen.get(idx)?;
+        b.castle = CastlingRights::none();
         if c == b'-' {
             idx += 1;
         } else {
-            if c == b'K' {
-                b.castle.0 = true;
-                idx += 1;
-                c = fen[idx];
-            }
-            if c == b'Q' {
-                b.castle.1 = true;
-                idx += 1;
-                c = fen[idx];
-            }
-            if c == b'k' {
-                b.castle.2 = true;
-                idx += 1;
-                c = fen[idx];
-            }
-            if c == b'q' {
-                b.castle.3 = true;
+            // Rights may come in any order, as `KQkq` or as Shredder-FEN rook files like `HAha`.
+            while let Some(&c) = fen.get(idx).filter(|&&c| c != b' ') {
+                let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+                let side = match c.to_ascii_lowercase() {
+                    b'k' | b'f'..=b'h' => CastlingSide::King,
+                    b'q' | b'a'..=b'd' => CastlingSide::Queen,
+                    _ => return None,
+                };
+                b.castle.add(colour, side);
                 idx += 1;
             }
         }
         idx += 1;
-        c = fen[idx];
+        c = *fen.get(idx)?;
         if c == b'-' {
             b.ep = None;
         } else {
-            let file = File::try_from(c - b'a').unwrap();
+            let file = File::try_from(c.checked_sub(b'a')?).ok()?;
             idx += 1;
-            c = fen[idx];
-            let rank = Rank::try_from(c - b'1').unwrap();
+            c = *fen.get(idx)?;
+            let rank = Rank::try_from(c.checked_sub(b'1')?).ok()?;
             b.ep = Some(Square::from_rank_file(rank, file));
         }
+        idx += 1;
+
+        // The halfmove clock is optional.
+        if fen.get(idx) == Some(&b' ') {
+            idx += 1;
+            while let Some(c) = fen.get(idx).filter(|c| c.is_ascii_digit()) {
+                b.halfmove = b.halfmove.saturating_mul(10).saturating_add(u32::from(c - b'0'));
+                idx += 1;
+            }
+        }
+
+        // So is the fullmove number, which counts from 1.
+        let mut fullmove = 0_u16;
+        if fen.get(idx) == Some(&b' ') {
+            idx += 1;
+            while let Some(c) = fen.get(idx).filter(|c| c.is_ascii_digit()) {
+                fullmove = fullmove.saturating_mul(10).saturating_add(u16::from(c - b'0'));
+                idx += 1;
+            }
+        }
+        b.ply = fullmove.saturating_sub(1).saturating_mul(2).saturating_add(u16::from(b.side == Colour::Black));
 
         b.data.rebuild_attacks();
+        b.drop_uncapturable_ep(b.side);
+        b.update_checkers();
 
         Some(b)
     }
 
+    /// List the legal moves with their types, one per line in sorted order, for diagnosing generation bugs.
+    #[must_use]
+    pub fn debug_moves(&self) -> String {
+        let mut moves = ArrayVec::from([Move::default(); 256]);
+        moves.set_len(0);
+        self.generate(&mut moves);
+
+        let mut lines = moves.iter().map(|m| format!("{} {:?}", m, m.kind)).collect::<Vec<_>>();
+        lines.sort();
+        lines.join("\n")
+    }
+
+    /// List each piece's index, square, colour and type, one per line in index order, for diagnosing piece-index bugs.
+    #[must_use]
+    pub fn debug_pieces(&self) -> String {
+        let lines = self
+            .data
+            .pieces()
+            .into_iter()
+            .map(|bit| {
+                let piece = self.data.piece_from_bit(bit);
+                format!("{:>2} {} {:?} {:?}", bit.into_inner(), self.data.square_of_piece(bit), bit.colour(), piece)
+            })
+            .collect::<Vec<_>>();
+        lines.join("\n")
+    }
+
+    /// Return the colour-flipped position: every piece moves to the vertically mirrored square and changes colour,
+    /// and the side to move, castling rights and en-passant square are flipped to match.
+    ///
+    /// # Panics
+    /// Panics if the position has more pieces than a side can hold, which a valid `Board` never does.
+    #[must_use]
+    pub fn mirror(&self) -> Self {
+        let mut b = Self::new();
+
+        for bit in self.data.pieces() {
+            let piece = self.data.piece_from_bit(bit);
+            let square = self.data.square_of_piece(bit).flip();
+            b.data.add_piece(piece, !bit.colour(), square, false).expect("mirrored position has too many pieces");
+        }
+
+        let mut castle = CastlingRights::none();
+        for colour in [Colour::White, Colour::Black] {
+            for side in [CastlingSide::King, CastlingSide::Queen] {
+                if self.castle.can_castle(colour, side) {
+                    castle.add(!colour, side);
+                }
+            }
+        }
+
+        b.side = !self.side;
+        b.castle = castle;
+        b.ep = self.ep.map(Square::flip);
+        b.halfmove = self.halfmove;
+        b.ply = self.ply ^ 1;
+        b.data.rebuild_attacks();
+        b.update_checkers();
+        b
+    }
+
+    /// Panic if the incrementally updated attack table differs from one rebuilt from scratch.
+    /// With the `consistency-checks` feature, `make` checks every position it returns, so update bugs are caught
+    /// on the move that causes them.
+    ///
+    /// # Panics
+    /// Panics when the attack table is inconsistent with the pieces on the board.
+    pub fn assert_consistent(&self) {
+        let mut fresh = self.clone();
+        fresh.data.rebuild_attacks();
+        assert!(self.data.attack_table() == fresh.data.attack_table(), "attack table is inconsistent in\n{}", self);
+    }
+
+    /// Write the position in Forsyth-Edwards Notation.
+    #[must_use]
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8_u8).rev() {
+            let mut empty = 0;
+            for file in 0..8_u8 {
+                // SAFETY: rank and file are both in bounds.
+                let square = unsafe { Square::from_u8_unchecked(rank * 8 + file) };
+                if let (Some(piece), Some(colour)) = (self.data.piece_from_square(square), self.data.colour_from_square(square)) {
+                    if empty > 0 {
+                        fen.push(char::from(b'0' + empty));
+                        empty = 0;
+                    }
+                    let c = match piece {
+                        Piece::Pawn => 'p',
+                        Piece::Knight => 'n',
+                        Piece::Bishop => 'b',
+                        Piece::Rook => 'r',
+                        Piece::Queen => 'q',
+                        Piece::King => 'k',
+                    };
+                    fen.push(if colour == Colour::White { c.to_ascii_uppercase() } else { c });
+                } else {
+                    empty += 1;
+                }
+            }
+            if empty > 0 {
+                fen.push(char::from(b'0' + empty));
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        let side = if self.side == Colour::White { 'w' } else { 'b' };
+        let ep = self.ep.map_or_else(|| "-".to_string(), |ep| ep.to_string());
+        format!("{} {} {} {} {} {}", fen, side, self.castle, ep, self.halfmove, self.ply / 2 + 1)
+    }
+
     /// Make a move on the board.
     ///
     /// # Panics
@@ -286,6 +484,8 @@ impl Board {
     #[must_use]
     pub fn make(&self, m: Move) -> Self {
         let mut b = self.clone();
+        b.halfmove = if self.is_irreversible(m) { 0 } else { self.halfmove + 1 };
+        b.ply = self.ply.saturating_add(1);
         match m.kind {
             MoveType::Normal => {
                 b.data.move_piece(m.from, m.dest);
@@ -294,6 +494,7 @@ impl Board {
             MoveType::DoublePush => {
                 b.data.move_piece(m.from, m.dest);
                 b.ep = m.from.relative_north(b.side);
+                b.drop_uncapturable_ep(!b.side);
             }
             MoveType::Capture => {
                 let piece_index = b
@@ -305,15 +506,8 @@ impl Board {
                 b.ep = None;
             }
             MoveType::Castle => {
-                if m.dest > m.from {
-                    let rook_from = m.dest.east().unwrap();
-                    let rook_to = m.dest.west().unwrap();
-                    b.data.move_piece(rook_from, rook_to);
-                } else {
-                    let rook_from = m.dest.west().unwrap().west().unwrap();
-                    let rook_to = m.dest.east().unwrap();
-                    b.data.move_piece(rook_from, rook_to);
-                }
+                let side = m.castle_side().expect("castling move has a side");
+                b.data.move_piece(rook_start(b.side, side), rook_castle_dest(b.side, side));
                 b.data.move_piece(m.from, m.dest);
                 b.ep = None;
             }
@@ -327,7 +521,9 @@ impl Board {
             MoveType::Promotion => {
                 let piece_index = b.data.piece_index(m.from).unwrap();
                 b.data.remove_piece(piece_index, true);
-                b.data.add_piece(m.prom.unwrap(), b.side, m.dest, true);
+                b.data
+                    .add_piece(m.prom.unwrap(), b.side, m.dest, true)
+                    .expect("promoting pawn was removed, so there is room for its replacement");
                 b.ep = None;
             }
             MoveType::CapturePromotion => {
@@ -335,51 +531,51 @@ impl Board {
                 let target_piece = b.data.piece_index(m.dest).unwrap();
                 b.data.remove_piece(source_piece, true);
                 b.data.remove_piece(target_piece, true);
-                b.data.add_piece(m.prom.unwrap(), b.side, m.dest, true);
+                b.data
+                    .add_piece(m.prom.unwrap(), b.side, m.dest, true)
+                    .expect("promoting pawn was removed, so there is room for its replacement");
                 b.ep = None;
             }
         }
 
-        let a1 = Square::from_rank_file(Rank::One, File::A);
-        let a8 = Square::from_rank_file(Rank::Eight, File::A);
-        let e1 = Square::from_rank_file(Rank::One, File::E);
-        let e8 = Square::from_rank_file(Rank::Eight, File::E);
-        let h1 = Square::from_rank_file(Rank::One, File::H);
-        let h8 = Square::from_rank_file(Rank::Eight, File::H);
-
-        if m.from == e1 {
-            b.castle.0 = false;
-            b.castle.1 = false;
-        }
-
-        if m.from == e8 {
-            b.castle.2 = false;
-            b.castle.3 = false;
-        }
-
-        if m.from == h1 || m.dest == h1 {
-            b.castle.0 = false;
-        }
-
-        if m.from == a1 || m.dest == a1 {
-            b.castle.1 = false;
-        }
-
-        if m.from == h8 || m.dest == h8 {
-            b.castle.2 = false;
-        }
-
-        if m.from == a8 || m.dest == a8 {
-            b.castle.3 = false;
+        // Moving the king or a rook, or capturing a rook, loses the matching rights.
+        for colour in [Colour::White, Colour::Black] {
+            for side in [CastlingSide::King, CastlingSide::Queen] {
+                let rook = rook_start(colour, side);
+                if m.from == king_start(colour) || m.from == rook || m.dest == rook {
+                    b.castle.remove(colour, side);
+                }
+            }
         }
 
         b.side = !b.side;
+        b.update_checkers();
+        #[cfg(feature = "consistency-checks")]
+        b.assert_consistent();
         b
     }
 
-    fn try_push_move(
+    /// Like Polyglot, only keep the en-passant square when a pawn of `capturer` could capture on it,
+    /// so hashes and FENs agree with other tools however the position was reached.
+    /// The attack tables must be up to date.
+    pub(crate) fn drop_uncapturable_ep(&mut self, capturer: Colour) {
+        let data = &self.data;
+        self.ep = self.ep.filter(|&ep| !(data.attacks_to(ep, capturer) & data.pawns()).empty());
+    }
+
+    /// Recompute the pieces giving check, after the pieces or side to move change.
+    pub(crate) fn update_checkers(&mut self) {
+        self.checkers = (self.data.kings() & Bitlist::mask_from_colour(self.side))
+            .peek()
+            .map_or_else(Bitlist::new, |king_index| {
+                let king_square = self.data.square_of_piece(king_index);
+                self.data.attacks_to(king_square, !self.side)
+            });
+    }
+
+    fn try_push_move<S: MoveSink>(
         &self,
-        v: &mut ArrayVec<[Move; 256]>,
+        v: &mut S,
         from: Square,
         dest: Square,
         kind: MoveType,
@@ -401,6 +597,15 @@ impl Board {
         v.push(Move::new(from, dest, kind, promotion_piece));
     }
 
+    /// Return the attacks to a square by the side to move and by its opponent, in that order.
+    fn attackers_split(&self, square: Square) -> (Bitlist, Bitlist) {
+        let (white, black) = self.data.attackers_both(square);
+        match self.side {
+            Colour::White => (white, black),
+            Colour::Black => (black, white),
+        }
+    }
+
     /// Find pinned pieces and handle them specially.
     ///
     /// # Panics
@@ -431,11 +636,7 @@ impl Board {
 
             let mut friendly_blocker = None;
             let mut enemy_blocker = None;
-            for square in pinner_square_16x8.ray_attacks(pinner_king_dir) {
-                if square == king_square {
-                    break;
-                }
-
+            for square in pinner_square.between(king_square) {
                 if let Some(piece_index) = self.data.piece_index(square) {
                     if self.data.colour_from_square(square) == Some(!self.side) {
                         match enemy_blocker {
@@ -491,7 +692,7 @@ impl Board {
     }
 
     /// Generate en-passant pawn moves.
-    fn generate_pawn_enpassant(&self, v: &mut ArrayVec<[Move; 256]>, pininfo: &PinInfo) {
+    fn generate_pawn_enpassant<S: MoveSink>(&self, v: &mut S, pininfo: &PinInfo) {
         if let Some(ep) = self.ep {
             for capturer in self
                 .data
@@ -506,7 +707,7 @@ impl Board {
     }
 
     /// Generate pawn-specific quiet moves.
-    fn generate_pawn_quiet(&self, v: &mut ArrayVec<[Move; 256]>, from: Square, pininfo: &PinInfo) {
+    fn generate_pawn_quiet<S: MoveSink>(&self, v: &mut S, from: Square, pininfo: &PinInfo) {
         let north = from.relative_north(self.side);
         if let Some(dest) = north {
             // Pawn single pushes.
@@ -562,24 +763,22 @@ impl Board {
 
     /// Generate moves when in check by a single piece.
     #[allow(clippy::too_many_lines)]
-    fn generate_single_check(&self, v: &mut ArrayVec<[Move; 256]>) {
+    fn generate_single_check<S: MoveSink>(&self, v: &mut S) {
         #[allow(clippy::unwrap_used)]
         let king_index = unsafe {
             (self.data.kings() & Bitlist::mask_from_colour(self.side)).peek_nonzero()
         };
         let king_square = self.data.square_of_piece(king_index);
-        let king_square_16x8 = Square16x8::from_square(king_square);
-        let attacker_bit = self.data.attacks_to(king_square, !self.side);
+        let attacker_bit = self.checkers;
         let attacker_index = unsafe {
             attacker_bit.peek_nonzero()
         };
         let attacker_piece = self.data.piece_from_bit(attacker_index);
         let attacker_square = self.data.square_of_piece(attacker_index);
-        let attacker_direction = attacker_square.direction(king_square);
 
         let pininfo = self.discover_pinned_pieces();
 
-        let add_pawn_block = |v: &mut ArrayVec<[Move; 256]>, from, dest, kind| {
+        let add_pawn_block = |v: &mut S, from, dest, kind| {
             if let Some(colour) = self.data.colour_from_square(from) {
                 if colour == self.side {
                     self.try_push_move(v, from, dest, kind, None, &pininfo);
@@ -587,7 +786,7 @@ impl Board {
             }
         };
 
-        let add_pawn_blocks = |v: &mut ArrayVec<[Move; 256]>, dest: Square| {
+        let add_pawn_blocks = |v: &mut S, dest: Square| {
             if let Some(from) = dest.relative_south(self.side) {
                 match self.data.piece_from_square(from) {
                     Some(Piece::Pawn) => add_pawn_block(v, from, dest, MoveType::Normal),
@@ -606,11 +805,10 @@ impl Board {
         };
 
         // Can we capture the attacker?
-        for capturer in self.data.attacks_to(attacker_square, self.side) {
+        let (capturers, defenders) = self.attackers_split(attacker_square);
+        for capturer in capturers {
             let from = self.data.square_of_piece(capturer);
-            if self.data.piece_from_bit(capturer) == Piece::King
-                && !self.data.attacks_to(attacker_square, !self.side).empty()
-            {
+            if self.data.piece_from_bit(capturer) == Piece::King && !defenders.empty() {
                 continue;
             }
             if self.data.piece_from_bit(capturer) == Piece::Pawn
@@ -675,12 +873,7 @@ impl Board {
 
         // Can we block the check?
         if let Piece::Bishop | Piece::Rook | Piece::Queen = attacker_piece {
-            let direction = king_square.direction(attacker_square).unwrap();
-            for dest in king_square_16x8.ray_attacks(direction) {
-                if dest == attacker_square {
-                    break;
-                }
-
+            for dest in king_square.between(attacker_square) {
                 // Piece moves.
                 for attacker in self
                     .data
@@ -704,6 +897,7 @@ impl Board {
         }
 
         // Can we move the king?
+        let unsafe_squares = self.unsafe_king_squares(king_square);
         for square in king_square.king_attacks() {
             let kind = if self.data.has_piece(square) {
                 if square == attacker_square
@@ -717,42 +911,24 @@ impl Board {
                 MoveType::Normal
             };
 
-            if !self.data.attacks_to(square, !self.side).empty() {
+            if unsafe_squares & (1 << square.into_inner()) != 0 {
                 // Moving into check is illegal.
                 continue;
             }
-            if let Some(attacker_direction) = attacker_direction {
-                // Slider attacks x-ray through the king to attack that square.
-                if let Some(xray_square) = king_square.travel(attacker_direction) {
-                    if matches!(attacker_piece, Piece::Bishop | Piece::Rook | Piece::Queen)
-                        && xray_square == square
-                    {
-                        continue;
-                    }
-                }
-            }
 
             v.push(Move::new(king_square, square, kind, None));
         }
     }
 
-    fn generate_double_check(&self, v: &mut ArrayVec<[Move; 256]>) {
+    fn generate_double_check<S: MoveSink>(&self, v: &mut S) {
         #[allow(clippy::unwrap_used)]
         let king_index = unsafe {
             (self.data.kings() & Bitlist::mask_from_colour(self.side)).peek_nonzero()
         };
         let king_square = self.data.square_of_piece(king_index);
-        let mut attacker_bits = self.data.attacks_to(king_square, !self.side);
-        let attacker1_index = attacker_bits.pop().unwrap();
-        let attacker1_piece = self.data.piece_from_bit(attacker1_index);
-        let attacker1_square = self.data.square_of_piece(attacker1_index);
-        let attacker1_direction = attacker1_square.direction(king_square);
-        let attacker2_index = attacker_bits.pop().unwrap();
-        let attacker2_piece = self.data.piece_from_bit(attacker2_index);
-        let attacker2_square = self.data.square_of_piece(attacker2_index);
-        let attacker2_direction = attacker2_square.direction(king_square);
 
-        // Can we move the king?
+        // Only the king can move.
+        let unsafe_squares = self.unsafe_king_squares(king_square);
         for square in king_square.king_attacks() {
             let kind = if self.data.has_piece(square) {
                 if self.data.colour_from_square(square) == Some(self.side) {
@@ -764,41 +940,46 @@ impl Board {
                 MoveType::Normal
             };
 
-            if !self.data.attacks_to(square, !self.side).empty() {
+            if unsafe_squares & (1 << square.into_inner()) != 0 {
                 // Moving into check is illegal.
                 continue;
             }
 
-            // Slider attacks x-ray through the king to attack that square.
-            if let Some(attacker1_direction) = attacker1_direction {
-                if let Some(xray_square) = king_square.travel(attacker1_direction) {
-                    if matches!(attacker1_piece, Piece::Bishop | Piece::Rook | Piece::Queen)
-                        && xray_square == square
-                    {
-                        continue;
-                    }
-                }
-            }
+            v.push(Move::new(king_square, square, kind, None));
+        }
+    }
 
-            if let Some(attacker2_direction) = attacker2_direction {
-                if let Some(xray_square) = king_square.travel(attacker2_direction) {
-                    if matches!(attacker2_piece, Piece::Bishop | Piece::Rook | Piece::Queen)
-                        && xray_square == square
-                    {
-                        continue;
-                    }
-                }
+    /// Return a mask of the squares next to `king_square` that the opponent attacks, read from the attack table.
+    /// The table stops a checking slider's attack at the king, so the square behind the king along its line,
+    /// which the king can't step back onto, is added as well.
+    fn unsafe_king_squares(&self, king_square: Square) -> u64 {
+        let mut squares = 0;
+        for square in king_square.king_attacks() {
+            if !self.data.attacks_to(square, !self.side).empty() {
+                squares |= 1 << square.into_inner();
+            }
+        }
+        for checker in self.checkers {
+            if !matches!(self.data.piece_from_bit(checker), Piece::Bishop | Piece::Rook | Piece::Queen) {
+                continue;
+            }
+            let behind = self.data.square_of_piece(checker).direction(king_square).and_then(|dir| king_square.travel(dir));
+            if let Some(behind) = behind {
+                squares |= 1 << behind.into_inner();
             }
-
-            v.push(Move::new(king_square, square, kind, None));
         }
+        squares
     }
 
     pub fn generate_captures(&self, v: &mut ArrayVec<[Move; 256]>) {
+        self.generate_captures_into(v);
+    }
+
+    fn generate_captures_into<S: MoveSink>(&self, v: &mut S) {
         let pininfo = self.discover_pinned_pieces();
 
         let mut find_attackers = |dest: Square| {
-            let attacks = self.data.attacks_to(dest, self.side);
+            let (attacks, defenders) = self.attackers_split(dest);
             for capturer in attacks & self.data.pawns() {
                 let from = self.data.square_of_piece(capturer);
                 if Rank::from(dest).is_relative_eighth(self.side) {
@@ -856,7 +1037,7 @@ impl Board {
             }
             for capturer in attacks & self.data.kings() {
                 let from = self.data.square_of_piece(capturer);
-                if !self.data.attacks_to(dest, !self.side).empty() {
+                if !defenders.empty() {
                     // Moving into check is illegal.
                     continue;
                 }
@@ -908,7 +1089,7 @@ impl Board {
         };
 
         let mut find_attackers = |dest: Square| -> bool {
-            let attacks = self.data.attacks_to(dest, self.side);
+            let (attacks, defenders) = self.attackers_split(dest);
             for capturer in attacks & self.data.pawns() {
                 let from = self.data.square_of_piece(capturer);
                 if Rank::from(dest).is_relative_eighth(self.side) {
@@ -981,7 +1162,7 @@ impl Board {
             }
             for capturer in attacks & self.data.kings() {
                 let from = self.data.square_of_piece(capturer);
-                if !self.data.attacks_to(dest, !self.side).empty() {
+                if !defenders.empty() {
                     // Moving into check is illegal.
                     continue;
                 }
@@ -1025,22 +1206,25 @@ impl Board {
     /// Panics when Lofty writes shitty code.
     #[allow(clippy::missing_inline_in_public_items)]
     pub fn generate(&self, v: &mut ArrayVec<[Move; 256]>) {
-        // Unless something has gone very badly wrong we have to have a king.
-        let king_index = unsafe {
-            (self.data.kings() & Bitlist::mask_from_colour(self.side)).peek_nonzero()
-        };
-        let king_square = self.data.square_of_piece(king_index);
-        let checks = self.data.attacks_to(king_square, !self.side);
+        self.generate_into(v);
+    }
 
-        if checks.count_ones() == 1 {
+    fn generate_into<S: MoveSink>(&self, v: &mut S) {
+        if self.checker_count() == 1 {
             return self.generate_single_check(v);
         }
-        if checks.count_ones() == 2 {
+        if self.checker_count() == 2 {
             return self.generate_double_check(v);
         }
 
+        // Unless something has gone very badly wrong we have to have a king.
+        let king_index = unsafe {
+            (self.data.kings() & Bitlist::mask_from_colour(self.side)).peek_nonzero()
+        };
+        let king_square = self.data.square_of_piece(king_index);
+
         let pininfo = self.discover_pinned_pieces();
-        self.generate_captures(v);
+        self.generate_captures_into(v);
 
         // Pawns.
         for pawn in self.data.pawns().and(Bitlist::mask_from_colour(self.side)) {
@@ -1048,70 +1232,205 @@ impl Board {
             self.generate_pawn_quiet(v, from, &pininfo);
         }
 
-        // General quiet move loop; pawns and kings handled separately.
-        for dest in 0_u8..64 {
-            // Squares will always be in range, so this will never panic.
-            let dest = unsafe { Square::from_u8_unchecked(dest) };
+        // General quiet move loop; pawns are handled above.
+        // Walking each piece's own targets avoids scanning every empty square for attackers.
+        for piece in self.data.pieces_of_colour(self.side).and(!self.data.pawns()) {
+            let from = self.data.square_of_piece(piece);
+            match self.data.piece_from_bit(piece) {
+                Piece::Knight => {
+                    for dest in from.knight_attacks() {
+                        if !self.data.has_piece(dest) {
+                            self.try_push_move(v, from, dest, MoveType::Normal, None, &pininfo);
+                        }
+                    }
+                }
+                Piece::King => {
+                    // It's illegal for kings to move to attacked squares; prune those out.
+                    let unsafe_squares = self.unsafe_king_squares(from);
+                    for dest in from.king_attacks() {
+                        if !self.data.has_piece(dest) && unsafe_squares & (1 << dest.into_inner()) == 0 {
+                            self.try_push_move(v, from, dest, MoveType::Normal, None, &pininfo);
+                        }
+                    }
+                }
+                slider => {
+                    for &dir in Direction::for_piece(slider) {
+                        for dest in Square16x8::from_square(from).ray_attacks(dir) {
+                            if self.data.has_piece(dest) {
+                                break;
+                            }
+                            self.try_push_move(v, from, dest, MoveType::Normal, None, &pininfo);
+                        }
+                    }
+                }
+            }
+        }
 
-            // Ignore captures.
-            if self.data.has_piece(dest) {
+        // Castling needs the squares between king and rook empty, and the king's path safe.
+        for side in [CastlingSide::King, CastlingSide::Queen] {
+            if !self.castle.can_castle(self.side, side) {
                 continue;
             }
-
-            // For every piece that attacks this square, find its location and add it to the move list.
-            for attacker in self
-                .data
-                .attacks_to(dest, self.side)
-                .and(!self.data.pawns())
-                //.and(!self.data.kings())
+            // A malformed FEN can grant rights without the king and rook on their home squares.
+            let rook = rook_start(self.side, side);
+            if king_square != king_start(self.side)
+                || self.data.piece_from_square(rook) != Some(Piece::Rook)
+                || self.data.colour_from_square(rook) != Some(self.side)
             {
-                // It's illegal for kings to move to attacked squares; prune those out.
-                if self.data.piece_from_bit(attacker) == Piece::King
-                    && !self.data.attacks_to(dest, !self.side).empty()
-                {
-                    continue;
-                }
-
-                let from = self.data.square_of_piece(attacker);
-                self.try_push_move(v, from, dest, MoveType::Normal, None, &pininfo);
+                continue;
+            }
+            let dest = king_castle_dest(self.side, side);
+            let empty = king_square.between(rook).iter().all(|&square| !self.data.has_piece(square));
+            let safe = || {
+                std::iter::once(king_square)
+                    .chain(king_square.between(dest))
+                    .chain(std::iter::once(dest))
+                    .all(|square| self.data.attacks_to(square, !self.side).empty())
+            };
+            if empty && safe() {
+                self.try_push_move(v, king_square, dest, MoveType::Castle, None, &pininfo);
             }
         }
+    }
 
-        // Kingside castling.
-        if (self.side == Colour::White && self.castle.0)
-            || (self.side == Colour::Black && self.castle.2)
-        {
-            let east1 = king_square.east().unwrap();
-            let east2 = east1.east().unwrap();
-            if self.data.attacks_to(king_square, !self.side).empty()
-                && !self.data.has_piece(east1)
-                && self.data.attacks_to(east1, !self.side).empty()
-                && !self.data.has_piece(east2)
-                && self.data.attacks_to(east2, !self.side).empty()
-            {
-                self.try_push_move(v, king_square, east2, MoveType::Castle, None, &pininfo);
-            }
+    /// Return the legal moves of the piece on `from`, such as to show where a selected piece can go.
+    #[must_use]
+    pub fn legal_moves_from(&self, from: Square) -> ArrayVec<[Move; 32]> {
+        let mut moves = ArrayVec::from([Move::default(); 256]);
+        moves.set_len(0);
+        self.generate(&mut moves);
+
+        let mut from_square = ArrayVec::new();
+        for m in moves.into_iter().filter(|m| m.from == from) {
+            from_square.push(m);
         }
+        from_square
+    }
 
-        // Queenside castling.
-        if (self.side == Colour::White && self.castle.1)
-            || (self.side == Colour::Black && self.castle.3)
-        {
-            let west1 = king_square.west().unwrap();
-            let west2 = west1.west().unwrap();
-            let west3 = west2.west().unwrap();
-            if self.data.attacks_to(king_square, !self.side).empty()
-                && !self.data.has_piece(west1)
-                && self.data.attacks_to(west1, !self.side).empty()
-                && !self.data.has_piece(west2)
-                && self.data.attacks_to(west2, !self.side).empty()
-                && !self.data.has_piece(west3)
-            {
-                self.try_push_move(v, king_square, west2, MoveType::Castle, None, &pininfo);
+    /// Generate the legal non-capturing moves that give check, for searches that only look at forcing moves.
+    /// Unlike evasions, these put the opponent in check rather than get out of it.
+    pub fn generate_checks(&self, v: &mut ArrayVec<[Move; 256]>) {
+        self.generate(v);
+        v.retain(|m| !m.is_capture() && self.gives_check(*m));
+    }
+
+    /// Generate the legal moves of the side to move's pieces of type `piece`, such as only its knight moves.
+    /// Castling counts as a king move.
+    pub fn generate_piece_moves(&self, piece: Piece, v: &mut ArrayVec<[Move; 256]>) {
+        let pieces = match piece {
+            Piece::Pawn => self.data.pawns(),
+            Piece::Knight => self.data.knights(),
+            Piece::Bishop => self.data.bishops(),
+            Piece::Rook => self.data.rooks(),
+            Piece::Queen => self.data.queens(),
+            Piece::King => self.data.kings(),
+        } & Bitlist::mask_from_colour(self.side);
+        self.generate_into(&mut PieceFilter { data: &self.data, pieces, inner: v });
+    }
+
+    /// Return a mask with bit `n` set for each square with index `n` the piece on `from` can legally move to,
+    /// or zero if it has no moves or doesn't belong to the side to move.
+    /// A castling king's destination is its two-square step, and an en-passant capture's is the square behind the pawn.
+    #[must_use]
+    pub fn legal_destinations(&self, from: Square) -> u64 {
+        let mut moves = ArrayVec::from([Move::default(); 256]);
+        moves.set_len(0);
+        self.generate(&mut moves);
+        moves.iter().filter(|m| m.from == from).fold(0, |mask, m| mask | (1 << m.dest.into_inner()))
+    }
+
+    /// Returns true if `m` is a legal move in this position, as `generate` would produce it.
+    /// Ordinary moves, captures and double pushes are checked against the attack tables without generating moves,
+    /// so a move from another position, such as a hash move or killer, can be vetted cheaply.
+    /// Castling, en passant and promotions are rare enough to check against the generated moves.
+    #[must_use]
+    pub fn is_legal(&self, m: Move) -> bool {
+        let Some(moving) = self.data.piece_index(m.from) else {
+            return false;
+        };
+        if self.data.colour_from_square(m.from) != Some(self.side) {
+            return false;
+        }
+
+        let piece = self.data.piece_from_bit(moving);
+        let attacks_dest = self.data.attacks_to(m.dest, self.side).contains(Bitlist::from(moving));
+        let pseudo_legal = match (m.kind, m.prom) {
+            (MoveType::Normal, None) => {
+                self.data.piece_index(m.dest).is_none()
+                    && if piece == Piece::Pawn {
+                        m.from.relative_north(self.side) == Some(m.dest) && !Rank::from(m.dest).is_relative_eighth(self.side)
+                    } else {
+                        attacks_dest
+                    }
             }
+            (MoveType::Capture, None) => {
+                self.data.colour_from_square(m.dest) == Some(!self.side)
+                    && self.data.piece_from_square(m.dest) != Some(Piece::King)
+                    && attacks_dest
+                    && !(piece == Piece::Pawn && Rank::from(m.dest).is_relative_eighth(self.side))
+            }
+            (MoveType::DoublePush, None) => {
+                let middle = m.from.relative_north(self.side);
+                piece == Piece::Pawn
+                    && Rank::from(m.dest).is_relative_fourth(self.side)
+                    && middle.and_then(|middle| middle.relative_north(self.side)) == Some(m.dest)
+                    && middle.is_some_and(|middle| self.data.piece_index(middle).is_none())
+                    && self.data.piece_index(m.dest).is_none()
+            }
+            _ => {
+                let mut moves = ArrayVec::from([Move::default(); 256]);
+                moves.set_len(0);
+                self.generate(&mut moves);
+                return moves.contains(&m);
+            }
+        };
+        if !pseudo_legal {
+            return false;
+        }
+
+        // The move must not leave the king attacked, whether by moving it, unpinning a piece or ignoring a check.
+        let king_square = if piece == Piece::King {
+            m.dest
+        } else {
+            let king_index = unsafe { (self.data.kings() & Bitlist::mask_from_colour(self.side)).peek_nonzero() };
+            self.data.square_of_piece(king_index)
+        };
+        !self.attacked_after(king_square, !self.side, m)
+    }
+
+    /// Generate the legal moves along with the type of piece making each one.
+    ///
+    /// # Panics
+    /// Panics if a generated move starts from an empty square, which would mean the board is corrupt.
+    pub fn generate_annotated(&self, v: &mut ArrayVec<[AnnotatedMove; 256]>) {
+        let mut moves = ArrayVec::from([Move::default(); 256]);
+        moves.set_len(0);
+        self.generate(&mut moves);
+
+        for m in moves {
+            let piece = self.data.piece_from_square(m.from).expect("legal move from an empty square");
+            v.push(AnnotatedMove { m, piece });
         }
     }
 
+    /// Return the number of legal moves, for callers that don't need the moves themselves.
+    /// The generator counts the moves as it finds them, without building a list.
+    #[must_use]
+    pub fn legal_move_count(&self) -> usize {
+        let mut counter = MoveCounter(0);
+        self.generate_into(&mut counter);
+        counter.0
+    }
+
+    /// Generate a vector of moves for `colour`, as if it were their turn.
+    ///
+    /// When `colour` is not the side to move, the position moves are generated from may be illegal:
+    /// the side to move may be in check, so `colour` could have moves that capture the king.
+    /// The en-passant square only applies to the side to move, so it is ignored.
+    pub fn generate_for(&self, colour: Colour, v: &mut ArrayVec<[Move; 256]>) {
+        self.with_side(colour).generate(v);
+    }
+
     #[must_use]
     pub const fn kings(&self) -> Bitlist {
         self.data.kings()
@@ -1123,6 +1442,38 @@ impl Board {
         self.data.pieces()
     }
 
+    /// Return the number of pieces `colour` has, including its king.
+    #[must_use]
+    pub const fn piece_count(&self, colour: Colour) -> u32 {
+        self.data.pieces().and(Bitlist::mask_from_colour(colour)).count_ones()
+    }
+
+    /// Return the number of `piece`s `colour` has.
+    #[must_use]
+    pub const fn piece_type_count(&self, piece: Piece, colour: Colour) -> u32 {
+        let pieces = match piece {
+            Piece::Pawn => self.data.pawns(),
+            Piece::Knight => self.data.knights(),
+            Piece::Bishop => self.data.bishops(),
+            Piece::Rook => self.data.rooks(),
+            Piece::Queen => self.data.queens(),
+            Piece::King => self.data.kings(),
+        };
+        pieces.and(Bitlist::mask_from_colour(colour)).count_ones()
+    }
+
+    /// The game phase from the non-pawn material left, from 256 with the starting material or more down to 0 with
+    /// only kings and pawns. Minor pieces count one, rooks two and queens four, out of 24 in the starting position.
+    #[must_use]
+    pub const fn phase(&self) -> u32 {
+        let material = self.data.knights().count_ones()
+            + self.data.bishops().count_ones()
+            + 2 * self.data.rooks().count_ones()
+            + 4 * self.data.queens().count_ones();
+        let material = if material > 24 { 24 } else { material };
+        (material * 256 + 12) / 24
+    }
+
     /// Given a piece index, return its piece type.
     #[must_use]
     pub fn piece_from_bit(&self, bit: PieceIndex) -> Piece {
@@ -1139,6 +1490,73 @@ impl Board {
         self.data.square_of_piece(bit)
     }
 
+    /// Given a piece index, return its piece type, colour and square.
+    #[must_use]
+    pub fn piece_info(&self, bit: PieceIndex) -> (Piece, Colour, Square) {
+        (self.data.piece_from_bit(bit), bit.colour(), self.data.square_of_piece(bit))
+    }
+
+    /// Return the square and type of each of `colour`'s pieces attacking `square`.
+    #[must_use]
+    pub fn attackers_of_type(&self, square: Square, colour: Colour) -> ArrayVec<[(Square, Piece); 16]> {
+        let mut attackers = ArrayVec::new();
+        for attacker in self.data.attacks_to(square, colour) {
+            attackers.push((self.data.square_of_piece(attacker), self.data.piece_from_bit(attacker)));
+        }
+        attackers
+    }
+
+    /// Return the set of occupied squares, as a bitboard indexed by square.
+    #[must_use]
+    pub fn occupied(&self) -> u64 {
+        self.colour_occupied(Colour::White) | self.colour_occupied(Colour::Black)
+    }
+
+    /// Return the set of squares occupied by `colour`'s pieces, as a bitboard indexed by square.
+    #[must_use]
+    pub fn colour_occupied(&self, colour: Colour) -> u64 {
+        let mut occupied = 0;
+        for piece in self.data.pieces_of_colour(colour) {
+            occupied |= 1 << self.data.square_of_piece(piece).into_inner();
+        }
+        occupied
+    }
+
+    /// Return the set of squares attacked by `colour`'s pawns, as a bitboard indexed by square.
+    #[must_use]
+    pub fn pawn_attacks(&self, colour: Colour) -> u64 {
+        let mut attacks = 0;
+        for pawn in self.data.pawns() & Bitlist::mask_from_colour(colour) {
+            for square in self.data.square_of_piece(pawn).pawn_attacks(colour) {
+                attacks |= 1 << square.into_inner();
+            }
+        }
+        attacks
+    }
+
+    /// Return the Zobrist hash of the position.
+    #[must_use]
+    pub fn hash(&self) -> u64 {
+        let mut hash = self.data.hash();
+        if self.side == Colour::Black {
+            hash ^= zobrist::side();
+        }
+        for (index, &right) in self.castle.to_array().iter().enumerate() {
+            if right {
+                hash ^= zobrist::castle(index);
+            }
+        }
+        if let Some(ep) = self.ep {
+            hash ^= zobrist::ep(File::from(ep));
+        }
+        hash
+    }
+
+    #[must_use]
+    pub const fn castling_rights(&self) -> CastlingRights {
+        self.castle
+    }
+
     #[must_use]
     pub const fn ep(&self) -> Option<Square> {
         self.ep
@@ -1150,12 +1568,135 @@ impl Board {
     }
 
     #[must_use]
-    pub fn in_check(&self) -> bool {
-        let king_index = unsafe {
-            (self.data.kings() & Bitlist::mask_from_colour(self.side)).peek_nonzero()
+    pub const fn in_check(&self) -> bool {
+        !self.checkers.empty()
+    }
+
+    /// The number of pieces giving check to the side to move.
+    #[must_use]
+    pub const fn checker_count(&self) -> u32 {
+        self.checkers.count_ones()
+    }
+
+    /// Returns true if a move puts the opponent in check.
+    #[must_use]
+    pub fn gives_check(&self, m: Move) -> bool {
+        self.make(m).in_check()
+    }
+
+    /// Returns true if `colour` would attack `square` once `m` is made, without making it.
+    /// Current attackers count unless `m` moves or captures them or blocks their line to `square`; to those are added
+    /// the attacks of the pieces `m` puts down, and of sliders whose line to `square` runs through a square it empties.
+    ///
+    /// # Panics
+    /// Panics if `m` starts from an empty square.
+    #[must_use]
+    pub fn attacked_after(&self, square: Square, colour: Colour, m: Move) -> bool {
+        let mover = self.data.piece_from_square(m.from).expect("move starts from an empty square");
+
+        // The squares `m` empties, and the pieces of the side to move it puts down.
+        let mut vacated: ArrayVec<[Square; 2]> = ArrayVec::new();
+        let mut placed: ArrayVec<[(Square, Piece); 2]> = ArrayVec::new();
+        vacated.push(m.from);
+        placed.push((m.dest, m.prom.unwrap_or(mover)));
+        match m.kind {
+            MoveType::Castle => {
+                let side = m.castle_side().expect("castling move has a side");
+                vacated.push(rook_start(self.side, side));
+                placed.push((rook_castle_dest(self.side, side), Piece::Rook));
+            }
+            MoveType::EnPassant => vacated.push(m.dest.relative_south(self.side).expect("en-passant square is on the board")),
+            _ => {}
+        }
+
+        let is_placed = |sq: Square| placed.iter().any(|&(placed, _)| placed == sq);
+        let piece_after = |sq: Square| {
+            if let Some(&(_, piece)) = placed.iter().find(|&&(placed, _)| placed == sq) {
+                return Some((piece, self.side));
+            }
+            if vacated.contains(&sq) {
+                return None;
+            }
+            Some((self.data.piece_from_square(sq)?, self.data.colour_from_square(sq)?))
         };
-        let king_square = self.data.square_of_piece(king_index);
-        !self.data.attacks_to(king_square, !self.side).empty()
+
+        // An attacker that stays put keeps its attack unless a piece lands between it and the square.
+        for attacker in self.data.attacks_to(square, colour) {
+            let from = self.data.square_of_piece(attacker);
+            if !vacated.contains(&from) && !is_placed(from) && !from.between(square).into_iter().any(is_placed) {
+                return true;
+            }
+        }
+
+        // The pieces `m` puts down attack from their new squares.
+        if self.side == colour {
+            for &(from, piece) in placed.iter() {
+                let attacks = match piece {
+                    Piece::Pawn => from.pawn_attacks(colour).any(|sq| sq == square),
+                    Piece::Knight => from.knight_attacks().any(|sq| sq == square),
+                    Piece::King => from.king_attacks().any(|sq| sq == square),
+                    Piece::Bishop | Piece::Rook | Piece::Queen => {
+                        from.direction(square).is_some_and(|dir| dir.valid_for_slider(piece))
+                            && from.between(square).into_iter().all(|sq| piece_after(sq).is_none())
+                    }
+                };
+                if attacks {
+                    return true;
+                }
+            }
+        }
+
+        // Emptying a square can open a slider's line through it.
+        for &empty in vacated.iter() {
+            let Some(dir) = square.direction(empty).filter(|dir| dir.diagonal() || dir.orthogonal()) else {
+                continue;
+            };
+            let mut sq = square.travel(dir);
+            while let Some(current) = sq {
+                if let Some((piece, piece_colour)) = piece_after(current) {
+                    if piece_colour == colour
+                        && matches!(piece, Piece::Bishop | Piece::Rook | Piece::Queen)
+                        && dir.valid_for_slider(piece)
+                    {
+                        return true;
+                    }
+                    break;
+                }
+                sq = current.travel(dir);
+            }
+        }
+
+        false
+    }
+
+    /// Returns true if no sequence of moves can undo `m`: it is a capture, castle, promotion, or pawn move.
+    #[must_use]
+    pub fn is_irreversible(&self, m: Move) -> bool {
+        m.kind.is_irreversible() || self.data.piece_from_square(m.from) == Some(Piece::Pawn)
+    }
+
+    /// The number of plies since the last irreversible move, for the fifty-move rule.
+    #[must_use]
+    pub const fn halfmove_clock(&self) -> u32 {
+        self.halfmove
+    }
+
+    /// The number of plies played since the start of the game, counting from the FEN's fullmove number.
+    /// Unlike the halfmove clock, irreversible moves don't reset it.
+    #[must_use]
+    pub const fn ply(&self) -> u16 {
+        self.ply
+    }
+
+    /// Return this position with `colour` to move.
+    fn with_side(&self, colour: Colour) -> Self {
+        let mut board = self.clone();
+        if board.side != colour {
+            board.side = colour;
+            board.ep = None;
+            board.update_checkers();
+        }
+        board
     }
 
     #[must_use]
@@ -1163,6 +1704,8 @@ impl Board {
         let mut board = self.clone();
         board.side = !board.side;
         board.ep = None;
+        board.ply = board.ply.saturating_add(1);
+        board.update_checkers();
         board
     }
 }
@@ -1176,3 +1719,894 @@ impl Board {
 } */
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::{Bitlist, Board, CastlingRights, CastlingSide};
+    use crate::{
+        square::{File, Rank},
+        test_support::{find_move, legal_moves},
+        AnnotatedMove, Colour, Move, MoveType, Piece, Square,
+    };
+    use std::convert::TryFrom;
+    use tinyvec::ArrayVec;
+
+    fn make_moves(board: &Board, moves: &[&str]) -> Board {
+        let mut board = board.clone();
+        for text in moves {
+            board = board.make(find_move(&board, text));
+        }
+        board
+    }
+
+    /// Generate moves like `Board::generate`, asserting that none of them leaves the mover's king in check.
+    fn generate_checked(board: &Board, v: &mut ArrayVec<[Move; 256]>) {
+        board.generate(v);
+        for &m in v.iter() {
+            assert!(!board.make(m).illegal(), "{} leaves the king in check in\n{}", m, board);
+        }
+    }
+
+    /// Play up to `plies` pseudo-random legal moves from `board`, calling `visit` on every position reached.
+    fn random_playout(board: &Board, seed: u64, plies: usize, mut visit: impl FnMut(&Board)) {
+        let mut state = seed;
+        let mut board = board.clone();
+        for _ in 0..plies {
+            visit(&board);
+
+            let moves = legal_moves(&board);
+            if moves.is_empty() {
+                break;
+            }
+
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            #[allow(clippy::cast_possible_truncation)]
+            let index = (state % moves.len() as u64) as usize;
+            board = board.make(moves[index]);
+        }
+    }
+
+    #[test]
+    fn random_positions_generate_legal_moves() {
+        let mut positions = 0;
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            for seed in 1..=50 {
+                random_playout(&board, seed * 0x9E37_79B9_7F4A_7C15, 200, |board| {
+                    let mut moves = ArrayVec::from([Move::default(); 256]);
+                    moves.set_len(0);
+                    generate_checked(board, &mut moves);
+                    assert_eq!(board.legal_move_count(), moves.len());
+                    positions += 1;
+                });
+            }
+        }
+        assert!(positions > 10_000);
+    }
+
+    #[test]
+    fn attacked_after_matches_make() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            for seed in 1..=10 {
+                random_playout(&board, seed * 0xC2B2_AE3D_27D4_EB4F, 100, |board| {
+                    let moves = legal_moves(board);
+                    for m in moves {
+                        let after = board.make(m);
+                        for square in 0..64 {
+                            let square = Square::try_from(square).unwrap();
+                            for colour in [Colour::White, Colour::Black] {
+                                assert_eq!(
+                                    board.attacked_after(square, colour, m),
+                                    !after.data.attacks_to(square, colour).empty(),
+                                    "{} after {} in {}",
+                                    square,
+                                    m,
+                                    board.to_fen()
+                                );
+                            }
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn random_games_keep_attacks_consistent() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            for seed in 1..=20 {
+                random_playout(&board, seed * 0xD6E8_FEB8_6659_FD93, 300, Board::assert_consistent);
+            }
+        }
+    }
+
+    #[test]
+    fn no_piece_attacks_itself() {
+        // A king counted among the attackers of its own square would never find a legal move.
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            for seed in 1..=20 {
+                random_playout(&board, seed * 0x94D0_49BB_1331_11EB, 300, |board| {
+                    for piece in board.pieces() {
+                        let square = board.square_of_piece(piece);
+                        assert!(
+                            !board.data.attacks_to(square, piece.colour()).contains(Bitlist::from_piece(piece)),
+                            "{:?} on {} attacks itself in {}",
+                            board.piece_from_bit(piece),
+                            square,
+                            board.to_fen()
+                        );
+                    }
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn generate_checks() {
+        // Nf6+ checks the king on e8. Nxd6+ does too, but captures; no other move checks.
+        let board = Board::from_fen("4k3/8/3p4/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let mut checks = ArrayVec::from([Move::default(); 256]);
+        checks.set_len(0);
+        board.generate_checks(&mut checks);
+        let mut checks = checks.iter().map(ToString::to_string).collect::<Vec<_>>();
+        checks.sort();
+        assert_eq!(checks, ["e4f6"]);
+    }
+
+    #[test]
+    fn captures_through_batteries_update_attacks() {
+        // Each capture removes an attacker and opens the line for the slider behind it.
+        for (fen, line) in [
+            ("4k3/4r3/4r3/8/8/4R3/4R3/4K3 w - - 0 1", ["e3e6", "e7e6", "e2e6"]),
+            ("4k3/7p/6b1/8/8/8/2B5/1Q2K3 w - - 0 1", ["c2g6", "h7g6", "b1g6"]),
+        ] {
+            let mut board = Board::from_fen(fen).unwrap();
+            for text in line {
+                board = board.make(find_move(&board, text));
+                board.assert_consistent();
+
+                let moves = legal_moves(&board);
+                for m in moves {
+                    board.make(m).assert_consistent();
+                }
+            }
+
+            let rebuilt = Board::from_fen(&board.to_fen()).unwrap();
+            for square in 0..64 {
+                let square = Square::try_from(square).unwrap();
+                for colour in [Colour::White, Colour::Black] {
+                    let squares = |board: &Board| {
+                        let attackers = board.data.attacks_to(square, colour);
+                        let mut squares = attackers.into_iter().map(|bit| board.square_of_piece(bit)).collect::<Vec<_>>();
+                        squares.sort();
+                        squares
+                    };
+                    assert_eq!(squares(&board), squares(&rebuilt), "{fen} {square}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn annotated_moves_carry_the_moving_piece() {
+        let board = Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+        let moves = legal_moves(&board);
+        let mut annotated = ArrayVec::from([AnnotatedMove::default(); 256]);
+        annotated.set_len(0);
+        board.generate_annotated(&mut annotated);
+
+        assert_eq!(annotated.len(), moves.len());
+        for (annotated, &m) in annotated.iter().zip(moves.iter()) {
+            assert!(annotated.m == m);
+            assert_eq!(Some(annotated.piece), board.piece_from_square(m.from), "{m}");
+        }
+
+        let piece_of = |text: &str| annotated.iter().find(|m| m.to_string() == text).map(|m| m.piece);
+        assert_eq!(piece_of("c4c5"), Some(Piece::Pawn));
+        assert_eq!(piece_of("f3d4"), Some(Piece::Knight));
+        assert_eq!(piece_of("b4c5"), Some(Piece::Bishop));
+        assert_eq!(piece_of("g1h1"), Some(Piece::King));
+    }
+
+    #[test]
+    fn castling_needs_the_rook_at_home() {
+        let castles = |fen: &str| {
+            let board = Board::from_fen(fen).unwrap();
+            let moves = legal_moves(&board);
+            let mut castles = moves.iter().filter(|m| m.kind == MoveType::Castle).map(ToString::to_string).collect::<Vec<_>>();
+            castles.sort();
+            castles
+        };
+
+        // The rights claim both rooks, but only the one on a1 is there.
+        assert_eq!(castles("4k3/8/8/8/8/8/8/R3K3 w KQ - 0 1"), ["e1c1"]);
+        // A rook of the wrong colour, or another piece, on the home square doesn't count.
+        assert!(castles("4k3/8/8/8/8/8/8/r3K2B w KQ - 0 1").is_empty());
+        // Nor does a rook when the king has left its square.
+        assert!(castles("4k3/8/8/8/8/8/8/R2K3R w KQ - 0 1").is_empty());
+    }
+
+    #[test]
+    fn generate_piece_moves() {
+        let piece_moves = |board: &Board, piece| {
+            let mut moves = ArrayVec::from([Move::default(); 256]);
+            moves.set_len(0);
+            board.generate_piece_moves(piece, &mut moves);
+            let mut moves = moves.iter().map(ToString::to_string).collect::<Vec<_>>();
+            moves.sort();
+            moves
+        };
+
+        let startpos = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(piece_moves(&startpos, Piece::Knight), ["b1a3", "b1c3", "g1f3", "g1h3"]);
+        assert_eq!(piece_moves(&startpos, Piece::Pawn).len(), 16);
+        assert!(piece_moves(&startpos, Piece::Queen).is_empty());
+
+        // Castling is a king move, and the pinned knight on d7 has none.
+        let board = Board::from_fen("3k4/3n4/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert!(piece_moves(&board, Piece::Knight).is_empty());
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(piece_moves(&board, Piece::King).contains(&"e1g1".to_string()));
+    }
+
+    #[test]
+    fn legal_destinations() {
+        let mask = |squares: &[Square]| squares.iter().fold(0, |mask, s| mask | 1 << s.into_inner());
+        let (d2, g1) = (Square::new(File::D, Rank::Two), Square::new(File::G, Rank::One));
+
+        // The bishop on d2 is pinned by the queen on a5 and may only move along the pin ray, including capturing it.
+        let board = Board::from_fen("4k3/8/8/q7/8/8/3B4/4K1N1 w - - 0 1").unwrap();
+        let ray = [Square::new(File::C, Rank::Three), Square::new(File::B, Rank::Four), Square::new(File::A, Rank::Five)];
+        assert_eq!(board.legal_destinations(d2), mask(&ray));
+        let knight = [Square::new(File::E, Rank::Two), Square::new(File::F, Rank::Three), Square::new(File::H, Rank::Three)];
+        assert_eq!(board.legal_destinations(g1), mask(&knight));
+
+        // Empty squares and the opponent's pieces have nowhere to go.
+        assert_eq!(board.legal_destinations(Square::new(File::D, Rank::Four)), 0);
+        assert_eq!(board.legal_destinations(Square::new(File::A, Rank::Five)), 0);
+
+        // Castling and en-passant destinations are included.
+        let board = Board::from_fen("4k3/8/8/3Pp3/8/8/8/4K2R w K e6 0 1").unwrap();
+        assert_ne!(board.legal_destinations(Square::new(File::E, Rank::One)) & mask(&[g1]), 0);
+        let pawn = [Square::new(File::D, Rank::Six), Square::new(File::E, Rank::Six)];
+        assert_eq!(board.legal_destinations(Square::new(File::D, Rank::Five)), mask(&pawn));
+    }
+
+    #[test]
+    fn legal_move_count_matches_perft() {
+        // The first ply of the perft suite's positions.
+        for (fen, count) in [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 20),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 48),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 14),
+            ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 6),
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 44),
+            ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 46),
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().legal_move_count(), count, "{fen}");
+        }
+
+        // Checkmate and stalemate both leave no moves.
+        assert_eq!(Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap().legal_move_count(), 0);
+        assert_eq!(Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap().legal_move_count(), 0);
+    }
+
+    #[test]
+    fn hash_matches_fen() {
+        let startpos =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let board = make_moves(&startpos, &["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1"]);
+        let fen = Board::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4",
+        )
+        .unwrap();
+        assert_eq!(board.hash(), fen.hash());
+    }
+
+    #[test]
+    fn uncapturable_ep_matches_fen() {
+        let startpos =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let board = make_moves(&startpos, &["e2e4"]);
+        let fen = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(board.hash(), fen.hash());
+        assert_eq!(board.to_fen(), fen.to_fen());
+        assert_eq!(fen.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+
+        // A square a pawn can capture on is kept.
+        let fen = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(fen.ep(), Some(Square::from_rank_file(Rank::Six, File::D)));
+    }
+
+    #[test]
+    fn is_legal_matches_generate() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            for seed in 1..=20 {
+                // Moves from two plies back are a ready supply of plausible but often illegal moves for the same side.
+                let (mut older, mut previous) = (Vec::new(), Vec::new());
+                random_playout(&board, seed * 0x9E37_79B9_7F4A_7C15, 100, |board| {
+                    let moves = legal_moves(board);
+                    for &m in older.iter().chain(previous.iter()).chain(moves.iter()) {
+                        assert_eq!(board.is_legal(m), moves.contains(&m), "{} {}", board.to_fen(), m);
+                    }
+                    older = std::mem::replace(&mut previous, moves.to_vec());
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn hash_transposition() {
+        let startpos =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let a = make_moves(&startpos, &["g1f3", "g8f6", "b1c3"]);
+        let b = make_moves(&startpos, &["b1c3", "g8f6", "g1f3"]);
+        assert_eq!(a.hash(), b.hash());
+        assert_ne!(a.hash(), startpos.hash());
+        assert_ne!(a.hash(), a.make_null().hash());
+    }
+
+    #[test]
+    fn see() {
+        let board = Board::from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+        assert_eq!(board.see(find_move(&board, "e1e5")), 100);
+
+        let board =
+            Board::from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1").unwrap();
+        assert_eq!(board.see(find_move(&board, "d3e5")), -200);
+
+        let board = Board::from_fen("4k3/8/2p5/3p4/4Q3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.see(find_move(&board, "e4d5")), -800);
+        assert!(board.see_ge(find_move(&board, "e4e5"), 0));
+    }
+
+    #[test]
+    fn see_king_recaptures_last() {
+        // The pawn on d5 is defended only by the king, which can't recapture while the rook also covers d5.
+        let board = Board::from_fen("8/8/4k3/3p4/8/2N5/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.see(find_move(&board, "c3d5")), 100);
+        assert_eq!(board.exchange_sequence(Square::new(File::D, Rank::Five)).len(), 1);
+
+        // Without the rook, the king takes the knight back.
+        let board = Board::from_fen("8/8/4k3/3p4/8/2N5/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.see(find_move(&board, "c3d5")), -200);
+        assert!(board.exchange_sequence(Square::new(File::D, Rank::Five)).is_empty());
+    }
+
+    #[test]
+    fn exchange_sequence() {
+        let e5 = Square::new(File::E, Rank::Five);
+        let names = |board: &Board, target| {
+            board.exchange_sequence(target).iter().map(|&(from, piece, balance)| (from.to_string(), piece, balance)).collect::<Vec<_>>()
+        };
+
+        // The knight on e5 is attacked by a pawn, knight and rook, and defended by a pawn and bishop.
+        let board = Board::from_fen("4k3/8/3p1b2/4n3/3P4/5N2/8/4RK2 w - - 0 1").unwrap();
+        assert_eq!(
+            names(&board, e5),
+            [
+                ("d4".to_string(), Piece::Pawn, 300),
+                ("d6".to_string(), Piece::Pawn, 200),
+                ("f3".to_string(), Piece::Knight, 300),
+                ("f6".to_string(), Piece::Bishop, 0),
+                ("e1".to_string(), Piece::Rook, 300),
+            ]
+        );
+        assert_eq!(board.exchange_sequence(e5).last().unwrap().2, board.see(find_move(&board, "d4e5")));
+
+        // Taking a defended pawn with a queen loses material, so the exchange never starts.
+        let board = Board::from_fen("4k3/8/2p5/3p4/4Q3/8/8/4K3 w - - 0 1").unwrap();
+        assert!(names(&board, Square::new(File::D, Rank::Five)).is_empty());
+        assert!(names(&board, Square::new(File::E, Rank::One)).is_empty());
+    }
+
+    #[test]
+    fn piece_type_count() {
+        let board = Board::from_fen("r1bqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RN2K2R w KQkq - 0 1").unwrap();
+        assert_eq!(board.piece_type_count(Piece::Pawn, Colour::White), 8);
+        assert_eq!(board.piece_type_count(Piece::Bishop, Colour::White), 0);
+        assert_eq!(board.piece_type_count(Piece::Bishop, Colour::Black), 2);
+        assert_eq!(board.piece_type_count(Piece::Knight, Colour::Black), 0);
+        assert_eq!(board.piece_type_count(Piece::Rook, Colour::White), 2);
+        assert_eq!(board.piece_type_count(Piece::King, Colour::Black), 1);
+    }
+
+    #[test]
+    fn capture_gain() {
+        let board = Board::from_fen("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(board.capture_gain(find_move(&board, "d1d5")), -800);
+        assert_eq!(board.capture_gain(find_move(&board, "d1d4")), 0);
+
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(board.capture_gain(find_move(&board, "e5d6")), 0);
+
+        let board = Board::from_fen("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.capture_gain(find_move(&board, "e7d8q")), 1200);
+        assert_eq!(board.capture_gain(find_move(&board, "e7e8q")), 0);
+    }
+
+    #[test]
+    fn capture_wins_material() {
+        let board = Board::from_fen("4k3/8/2p5/3n4/8/8/3R4/4K1N1 w - - 0 1").unwrap();
+        assert!(!board.capture_wins_material(find_move(&board, "d2d5")));
+        assert!(!board.capture_wins_material(find_move(&board, "g1f3")));
+
+        let board = Board::from_fen("4k3/8/8/3n4/8/4N3/8/4K3 w - - 0 1").unwrap();
+        assert!(board.capture_wins_material(find_move(&board, "e3d5")));
+
+        let board = Board::from_fen("4k3/8/2p5/3n4/8/4N3/8/4K3 w - - 0 1").unwrap();
+        assert!(!board.capture_wins_material(find_move(&board, "e3d5")));
+    }
+
+    #[test]
+    fn is_quiet() {
+        let hanging_queen = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        assert!(!hanging_queen.is_quiet());
+
+        let locked_pawns =
+            Board::from_fen("4k3/1p3p2/1Pp2Pp1/2P3P1/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(locked_pawns.is_quiet());
+
+        let in_check = Board::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert!(!in_check.is_quiet());
+    }
+
+    #[test]
+    fn too_many_pieces_in_fen() {
+        assert!(Board::from_fen("4k3/8/8/8/NNNNNNNN/PPPPPPPP/8/4K3 w - - 0 1").is_none());
+        assert!(Board::from_fen("4k3/8/8/8/NNNNNNN1/PPPPPPPP/8/4K3 w - - 0 1").is_some());
+    }
+
+    #[test]
+    fn castling_rights_in_any_notation() {
+        let rights = |castling| {
+            Board::from_fen(&format!("r3k2r/8/8/8/8/8/8/R3K2R w {castling} - 0 1")).unwrap().castling_rights().to_string()
+        };
+        assert_eq!(rights("KQkq"), "KQkq");
+        assert_eq!(rights("Qk"), "Qk");
+        assert_eq!(rights("qkQK"), "KQkq");
+        assert_eq!(rights("HAha"), "KQkq");
+        assert_eq!(rights("Ah"), "Qk");
+        assert_eq!(rights("-"), "-");
+        assert!(Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KX - 0 1").is_none());
+    }
+
+    #[test]
+    fn castling_rights() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(board.castling_rights(), CastlingRights::all());
+
+        let king_moved = make_moves(&board, &["e1e2"]);
+        assert!(!king_moved.castling_rights().can_castle(Colour::White, CastlingSide::King));
+        assert!(!king_moved.castling_rights().can_castle(Colour::White, CastlingSide::Queen));
+        assert!(king_moved.castling_rights().can_castle(Colour::Black, CastlingSide::King));
+
+        let rook_moved = make_moves(&board, &["h1h2", "a8a7"]);
+        assert!(!rook_moved.castling_rights().white_king());
+        assert!(rook_moved.castling_rights().white_queen());
+        assert!(rook_moved.castling_rights().black_king());
+        assert!(!rook_moved.castling_rights().black_queen());
+
+        let rook_captured = make_moves(&board, &["a1a8"]);
+        assert!(!rook_captured.castling_rights().white_queen());
+        assert!(!rook_captured.castling_rights().black_queen());
+        assert_eq!(rook_captured.castling_rights().to_string(), "Kk");
+    }
+
+    #[test]
+    fn irreversible_moves() {
+        let board = Board::from_fen("r3k2r/8/8/8/3p4/5N2/4P3/R3K2R w KQkq - 7 20").unwrap();
+        assert_eq!(board.halfmove_clock(), 7);
+
+        let knight = find_move(&board, "f3g5");
+        assert!(!knight.kind.is_irreversible());
+        assert!(!board.is_irreversible(knight));
+        assert_eq!(board.make(knight).halfmove_clock(), 8);
+
+        for text in ["f3d4", "e2e3", "e2e4", "e1g1", "a1a8"] {
+            let m = find_move(&board, text);
+            assert!(board.is_irreversible(m), "{} should be irreversible", text);
+            assert_eq!(board.make(m).halfmove_clock(), 0);
+        }
+        assert!(!find_move(&board, "e2e3").kind.is_irreversible());
+    }
+
+    #[test]
+    fn ply_counts_moves_from_the_fen() {
+        let board = Board::from_fen("r3k2r/8/8/8/3p4/5N2/4P3/R3K2R w KQkq - 7 20").unwrap();
+        assert_eq!(board.ply(), 38);
+
+        let played = make_moves(&board, &["e2e4", "d4e3", "f3g5"]);
+        assert_eq!(played.ply(), 41);
+        assert_eq!(played.halfmove_clock(), 1);
+        assert!(played.to_fen().ends_with(" 21"));
+        assert_eq!(played.make_null().ply(), 42);
+
+        assert_eq!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap().ply(), 1);
+        assert_eq!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap().ply(), 0);
+    }
+
+    #[test]
+    fn pawn_attacks() {
+        let board = Board::from_fen("4k3/3p4/8/8/8/8/P3P2P/4K3 w - - 0 1").unwrap();
+
+        // a2 attacks b3; e2 attacks d3 and f3; h2 attacks g3.
+        assert_eq!(board.pawn_attacks(Colour::White), (1 << 17) | (1 << 19) | (1 << 21) | (1 << 22));
+        // d7 attacks c6 and e6.
+        assert_eq!(board.pawn_attacks(Colour::Black), (1 << 42) | (1 << 44));
+    }
+
+    #[test]
+    fn generate_for_either_side() {
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let count = |colour| {
+            let mut moves = ArrayVec::from([Move::default(); 256]);
+            moves.set_len(0);
+            board.generate_for(colour, &mut moves);
+            moves.len()
+        };
+
+        assert_eq!(count(Colour::White), 20);
+        assert_eq!(count(Colour::Black), 20);
+    }
+
+    #[test]
+    fn checker_count() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(!board.in_check());
+        assert_eq!(board.checker_count(), 0);
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert!(board.in_check());
+        assert_eq!(board.checker_count(), 1);
+
+        // Nd6 is a double check, from the knight and the rook behind it.
+        let board = Board::from_fen("4k3/8/8/8/4N3/8/8/4RK2 w - - 0 1").unwrap();
+        let board = board.make(find_move(&board, "e4d6"));
+        assert!(board.in_check());
+        assert_eq!(board.checker_count(), 2);
+        assert_eq!(board.make_null().checker_count(), 0);
+    }
+
+    #[test]
+    fn legal_moves_from() {
+        let targets = |board: &Board, from: &str| {
+            let from = from.as_bytes();
+            let from = Square::from_rank_file(
+                Rank::try_from(from[1] - b'1').unwrap(),
+                File::try_from(from[0] - b'a').unwrap(),
+            );
+            let mut moves = board.legal_moves_from(from).iter().map(ToString::to_string).collect::<Vec<_>>();
+            moves.sort();
+            moves
+        };
+
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(targets(&board, "g1"), ["g1f3", "g1h3"]);
+        assert!(targets(&board, "e4").is_empty());
+        assert!(targets(&board, "e7").is_empty());
+
+        // The knight can't leave its pin, and the rook can only move along its own.
+        let board = Board::from_fen("k3r3/8/8/b7/4R3/8/3N4/4K3 w - - 0 1").unwrap();
+        assert!(targets(&board, "d2").is_empty());
+        assert_eq!(targets(&board, "e4"), ["e4e2", "e4e3", "e4e5", "e4e6", "e4e7", "e4e8"]);
+    }
+
+    #[test]
+    fn occupied() {
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(board.occupied().count_ones(), 32);
+        assert_eq!(board.colour_occupied(Colour::White), 0xFFFF);
+        assert_eq!(board.colour_occupied(Colour::Black), 0xFFFF << 48);
+
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(board.occupied().count_ones(), board.pieces().count_ones());
+        assert_eq!(board.colour_occupied(Colour::White) & board.colour_occupied(Colour::Black), 0);
+    }
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 13 1",
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn fen_ignores_surrounding_whitespace() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - -",
+        ] {
+            let trimmed = Board::from_fen(fen).unwrap();
+            for padded in [format!(" {fen}"), format!("{fen}\r\n"), format!("\t {fen} \n")] {
+                let board = Board::from_fen(&padded).unwrap();
+                assert_eq!(board.to_fen(), trimmed.to_fen());
+                assert_eq!(board.hash(), trimmed.hash());
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let m = find_move(&board, "e5f7");
+
+        let json = serde_json::to_string(&m).unwrap();
+        let m2: Move = serde_json::from_str(&json).unwrap();
+        assert!(m == m2);
+
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(json, format!("\"{}\"", board.to_fen()));
+        let board2: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(board2.hash(), board.hash());
+        assert_eq!(board2.to_fen(), board.to_fen());
+
+        // Malformed and illegal positions are errors, not panics.
+        for json in [
+            r#""""#,
+            r#""   ""#,
+            r#""rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR""#,
+            r#""4k3/8/8/8/8/8/8/4K3 w""#,
+            r#""4k3/8/8/8/8/8/8/4K3 w - -\u0000 0 1""#,
+            r#""4k3/8/8/8/8/8/8/4K3 w - z9 0 1""#,
+            r#""4k3/8/8/8/8/8/8/4K3 w - A3 0 1""#,
+            r#""8/8/8/8/8/8/8/8 w - - 0 1""#,
+            r#""4k3/8/8/8/8/8/8/8 w - - 0 1""#,
+        ] {
+            assert!(serde_json::from_str::<Board>(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn attackers_of_type() {
+        // d5 is attacked by the pawn on e4 and the knight on c3, and defended by the knight on f6.
+        let board = Board::from_fen("4k3/8/5n2/3p4/4P3/2N5/8/4K3 w - - 0 1").unwrap();
+        let d5 = Square::from_rank_file(Rank::Five, File::D);
+
+        let mut attackers = board.attackers_of_type(d5, Colour::White).into_iter().collect::<Vec<_>>();
+        attackers.sort_by_key(|(square, _)| *square);
+        assert_eq!(
+            attackers,
+            [
+                (Square::from_rank_file(Rank::Three, File::C), Piece::Knight),
+                (Square::from_rank_file(Rank::Four, File::E), Piece::Pawn),
+            ]
+        );
+
+        let defenders = board.attackers_of_type(d5, Colour::Black);
+        assert_eq!(defenders.as_slice(), [(Square::from_rank_file(Rank::Six, File::F), Piece::Knight)]);
+    }
+
+    #[test]
+    fn en_passant_pins() {
+        let generates = |board: &Board, text: &str| {
+            let moves = legal_moves(board);
+            moves.iter().any(|m| m.to_string() == text)
+        };
+
+        // Capturing removes both pawns from the rank, exposing the king to a rook or queen along it.
+        // The same goes for a pin along a file or diagonal that the capture leaves.
+        for (fen, text) in [
+            ("8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1", "e4d3"),
+            ("8/8/8/8/k2Pp2R/8/8/3K4 b - d3 0 1", "e4d3"),
+            ("8/8/8/8/Q2Pp2k/8/8/3K4 b - d3 0 1", "e4d3"),
+            ("3k4/8/8/K2pP2r/8/8/8/8 w - d6 0 1", "e5d6"),
+            ("4k3/8/8/8/3Pp3/8/8/4RK2 b - d3 0 1", "e4d3"),
+            ("8/1B6/8/8/3Pp3/8/6k1/4K3 b - d3 0 1", "e4d3"),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            assert!(!generates(&board, text), "{}", fen);
+        }
+
+        // Capturing along the pin, capturing the checking pawn, and a piece behind the capturer on the rank.
+        for (fen, text, after) in [
+            ("8/8/6k1/8/3Pp3/8/2B5/4K3 b - d3 0 1", "e4d3", "8/8/6k1/8/8/3p4/2B5/4K3 w - - 0 1"),
+            ("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1", "e4d3", "8/8/8/2k5/8/3p4/8/4K3 w - - 0 1"),
+            ("8/8/8/8/k1PPp2Q/8/8/3K4 b - d3 0 1", "e4d3", "8/8/8/8/k1P4Q/3p4/8/3K4 w - - 0 1"),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            assert!(generates(&board, text), "{}", fen);
+            let board = board.make(find_move(&board, text));
+            assert_eq!(board.hash(), Board::from_fen(after).unwrap().hash(), "{fen}");
+            assert!(board.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn double_check_escapes() {
+        let escapes = |fen: &str| {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.checker_count(), 2, "{fen}");
+            let moves = legal_moves(&board);
+            let mut moves = moves.iter().map(ToString::to_string).collect::<Vec<_>>();
+            moves.sort();
+            moves
+        };
+
+        // A discovered rook check and a direct knight check: the king can't step back along the rook's file.
+        assert_eq!(escapes("4k3/8/3N4/8/8/8/8/4RK2 b - - 0 1"), ["e8d7", "e8d8", "e8f8"]);
+
+        // The king may take an undefended checker, but nothing else may, even though the rook attacks it.
+        assert_eq!(escapes("4k3/r2B4/8/8/8/8/8/4RK2 b - - 0 1"), ["e8d7", "e8d8", "e8f7", "e8f8"]);
+
+        // Once the checker is defended, the king can't take it either.
+        assert_eq!(escapes("4k3/r2B4/2P5/8/8/8/8/4RK2 b - - 0 1"), ["e8d8", "e8f7", "e8f8"]);
+
+        // Boxed in by its own pieces, the only escape from a rook and knight is to the square the knight doesn't cover.
+        assert_eq!(escapes("7k/6p1/6N1/8/8/8/8/6KR b - - 0 1"), ["h8g8"]);
+        assert!(escapes("6rk/6p1/6N1/8/8/8/8/6KR b - - 0 1").is_empty());
+    }
+
+    #[test]
+    fn en_passant_square_needs_a_capturer() {
+        let startpos = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let board = make_moves(&startpos, &["e2e4"]);
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        let fen = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(board.hash(), fen.hash());
+
+        // With a Black pawn on d4, the capture is possible and the square is kept.
+        let board = make_moves(&startpos, &["e2e4", "d7d5", "e4e5", "d5d4", "c2c4"]);
+        assert_eq!(board.to_fen(), "rnbqkbnr/ppp1pppp/8/4P3/2Pp4/8/PP1P1PPP/RNBQKBNR b KQkq c3 0 3");
+        let board = board.make(find_move(&board, "d4c3"));
+        assert_eq!(board.to_fen(), "rnbqkbnr/ppp1pppp/8/4P3/8/2p5/PP1P1PPP/RNBQKBNR w KQkq - 0 4");
+    }
+
+    #[test]
+    fn castling_legality() {
+        let castles = |fen: &str| {
+            let board = Board::from_fen(fen).unwrap();
+            let moves = legal_moves(&board);
+            let mut castles = moves.iter().filter(|m| m.kind == MoveType::Castle).map(ToString::to_string).collect::<Vec<_>>();
+            castles.sort();
+            castles
+        };
+
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"), ["e1c1", "e1g1"]);
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1"), ["e8c8", "e8g8"]);
+
+        // Not out of check.
+        assert!(castles("r3k2r/8/8/4q3/8/8/8/R3K2R w KQkq - 0 1").is_empty());
+
+        // Not through or into an attacked square.
+        assert_eq!(castles("r3k2r/8/8/8/2b5/8/8/R3K2R w KQkq - 0 1"), ["e1c1"]);
+        assert_eq!(castles("r3k2r/8/8/8/8/7n/8/R3K2R w KQkq - 0 1"), ["e1c1"]);
+        assert_eq!(castles("3rk2r/8/8/8/8/8/8/R3K2R w KQk - 0 1"), ["e1g1"]);
+        assert_eq!(castles("2r1k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1"), ["e1g1"]);
+
+        // The king doesn't pass b1, so it may be attacked, but it must still be empty.
+        assert_eq!(castles("1r2k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1"), ["e1c1", "e1g1"]);
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/RN2K2R w KQkq - 0 1"), ["e1g1"]);
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/Rn2K2R w KQkq - 0 1"), ["e1g1"]);
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3K1NR w KQkq - 0 1"), ["e1c1"]);
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/R3KB1R w KQkq - 0 1"), ["e1c1"]);
+    }
+
+    #[test]
+    fn debug_pieces() {
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let dump = board.debug_pieces();
+        let lines = dump.lines().collect::<Vec<_>>();
+
+        // White's pieces take indices 0 to 15 and Black's 16 to 31.
+        assert_eq!(lines.len(), 32);
+        for (index, line) in lines.iter().enumerate() {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            assert_eq!(fields[0].parse::<usize>().unwrap(), index);
+            let (colour, ranks) = if index <= 15 { ("White", ['1', '2']) } else { ("Black", ['7', '8']) };
+            assert_eq!(fields[2], colour, "{line}");
+            assert!(fields[1].ends_with(ranks), "{}", line);
+        }
+        assert_eq!(lines[12], "12 e1 White King");
+        assert_eq!(lines[19], "19 d8 Black Queen");
+    }
+
+    #[test]
+    fn phase() {
+        let phase = |fen| Board::from_fen(fen).unwrap().phase();
+        assert_eq!(phase("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), 256);
+        assert_eq!(phase("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1"), 0);
+        assert_eq!(phase("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1"), 85);
+
+        // Promoted pieces can't push the phase past the opening.
+        assert_eq!(phase("rnbqkbnr/8/8/8/8/8/8/QQQQKQQQ w - - 0 1"), 256);
+
+        // Each piece taken moves the phase smoothly towards the endgame.
+        assert!(phase("r3k3/8/8/8/8/8/8/R3K3 w - - 0 1") > phase("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn piece_info() {
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let mut pawns = [0; 2];
+        for bit in board.pieces() {
+            let (piece, colour, square) = board.piece_info(bit);
+            assert_eq!(board.piece_from_square(square), Some(piece));
+            if piece == Piece::Pawn {
+                assert_eq!(Rank::from(square), if colour == Colour::White { Rank::Two } else { Rank::Seven });
+                pawns[colour as usize] += 1;
+            }
+        }
+        assert_eq!(pawns, [8, 8]);
+    }
+
+    #[test]
+    fn mirror() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/Pp2P3/2N2Q1p/1PPBBPPP/R3K2R b Kq a3 0 1").unwrap();
+        let mirrored = board.mirror();
+        assert_eq!(
+            mirrored.to_fen(),
+            "r3k2r/1ppbbppp/2n2q1P/pP2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R w Qk a6 0 1"
+        );
+        assert_eq!(mirrored.mirror().to_fen(), board.to_fen());
+        assert_eq!(crate::perft(&mirrored, 3), crate::perft(&board, 3));
+    }
+
+    #[test]
+    fn debug_moves() {
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let dump = board.debug_moves();
+        let lines = dump.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 20);
+        assert_eq!(lines.iter().filter(|line| line.ends_with(" DoublePush")).count(), 8);
+        assert_eq!(lines.iter().filter(|line| line.ends_with(" Normal")).count(), 12);
+        assert_eq!(lines[0], "a2a3 Normal");
+        assert!(lines.contains(&"e2e4 DoublePush"));
+        assert!(lines.contains(&"g1f3 Normal"));
+    }
+
+    #[test]
+    fn promotion_with_sixteen_pieces() {
+        // White has all sixteen piece slots in use, so promoting relies on the pawn's slot being freed first.
+        let board = Board::from_fen("r3k3/1P6/8/8/8/8/PPPPPPP1/RNBQKBNR w KQq - 0 1").unwrap();
+        assert_eq!(board.piece_count(Colour::White), 16);
+
+        for (text, after) in [
+            ("b7b8q", "rQ2k3/8/8/8/8/8/PPPPPPP1/RNBQKBNR b KQq - 0 1"),
+            ("b7b8n", "rN2k3/8/8/8/8/8/PPPPPPP1/RNBQKBNR b KQq - 0 1"),
+            ("b7a8q", "Q3k3/8/8/8/8/8/PPPPPPP1/RNBQKBNR b KQ - 0 1"),
+        ] {
+            let board = board.make(find_move(&board, text));
+            assert!(board.validate().is_ok(), "{}", text);
+            assert_eq!(board.piece_count(Colour::White), 16);
+
+            let fen = Board::from_fen(after).unwrap();
+            assert_eq!(board.hash(), fen.hash(), "{text}");
+            assert_eq!(crate::perft(&board, 2), crate::perft(&fen, 2), "{text}");
+        }
+    }
+}
@@ -0,0 +1,245 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::Board;
+use crate::{
+    colour::Colour,
+    piece::Piece,
+    square::{File, Rank, Square},
+};
+
+/// Why a game was drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrawReason {
+    /// The side to move has no legal moves but is not in check.
+    Stalemate,
+    /// Neither side has enough material to checkmate.
+    InsufficientMaterial,
+    /// A hundred plies have passed without a capture or pawn move.
+    FiftyMoves,
+    /// The same position has occurred three times.
+    Repetition,
+}
+
+/// The result of a finished game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// A side delivered checkmate.
+    Win(Colour),
+    /// The game is drawn.
+    Draw(DrawReason),
+}
+
+impl Board {
+    /// Return the result of the game if it is over, or `None` if play continues.
+    /// `history` holds the hashes of the positions before this one, oldest first, for detecting repetition.
+    #[must_use]
+    pub fn outcome(&self, history: &[u64]) -> Option<Outcome> {
+        if self.legal_move_count() == 0 {
+            return Some(if self.in_check() {
+                Outcome::Win(!self.side)
+            } else {
+                Outcome::Draw(DrawReason::Stalemate)
+            });
+        }
+
+        if self.insufficient_material() {
+            return Some(Outcome::Draw(DrawReason::InsufficientMaterial));
+        }
+
+        if self.halfmove >= 100 {
+            return Some(Outcome::Draw(DrawReason::FiftyMoves));
+        }
+
+        if self.repetitions(history) >= 2 {
+            return Some(Outcome::Draw(DrawReason::Repetition));
+        }
+
+        None
+    }
+
+    /// The key identifying this position when looking for repetitions: its Zobrist hash.
+    #[must_use]
+    pub fn repetition_key(&self) -> u64 {
+        self.hash()
+    }
+
+    /// Returns true if this position occurred earlier in `history`, the repetition keys of the positions before
+    /// this one, oldest first.
+    /// Only the last `halfmove_clock` entries are scanned, since positions before an irreversible move can't recur.
+    #[must_use]
+    pub fn can_repeat_within(&self, history: &[u64]) -> bool {
+        self.repetitions(history) > 0
+    }
+
+    /// How many times this position occurred in `history` since the last irreversible move.
+    fn repetitions(&self, history: &[u64]) -> usize {
+        let key = self.repetition_key();
+        history.iter().rev().take(self.halfmove as usize).filter(|&&h| h == key).count()
+    }
+
+    /// Returns true if the side to move is not in check but has no legal moves.
+    #[must_use]
+    pub fn is_stalemate(&self) -> bool {
+        if self.in_check() {
+            return false;
+        }
+        self.legal_move_count() == 0
+    }
+
+    /// Returns true if this is king, bishop and rook pawn against king where the bishop can't cover the promotion
+    /// square and the defending king can get to that corner first, which is a draw however the stronger side plays.
+    #[must_use]
+    pub fn is_wrong_bishop_rook_pawn_draw(&self) -> bool {
+        if self.data.pieces().count_ones() != 4 {
+            return false;
+        }
+        let (Some(pawn), Some(bishop)) = (self.data.pawns().into_iter().next(), self.data.bishops().into_iter().next())
+        else {
+            return false;
+        };
+        let strong = pawn.colour();
+        if bishop.colour() != strong {
+            return false;
+        }
+
+        let pawn_square = self.data.square_of_piece(pawn);
+        let file = File::from(pawn_square);
+        if !matches!(file, File::A | File::H) {
+            return false;
+        }
+        let corner = Square::from_rank_file(if strong == Colour::White { Rank::Eight } else { Rank::One }, file);
+        if self.data.square_of_piece(bishop).square_colour() == corner.square_colour() {
+            return false;
+        }
+
+        let mut kings = [corner; 2];
+        for king in self.data.kings() {
+            kings[king.colour() as usize] = self.data.square_of_piece(king);
+        }
+        let (strong_distance, weak_distance) = (
+            kings[strong as usize].chebyshev_distance(corner),
+            kings[!strong as usize].chebyshev_distance(corner),
+        );
+        // Having the move is worth a step.
+        let weak_distance = if self.side == strong { weak_distance } else { weak_distance.saturating_sub(1) };
+        weak_distance <= 1 || (weak_distance < pawn_square.steps_to_promotion(strong) && weak_distance < strong_distance)
+    }
+
+    /// Returns true if neither side can possibly checkmate: only kings, with at most a single minor piece,
+    /// or bishops that are all on squares of the same colour.
+    fn insufficient_material(&self) -> bool {
+        let mut minors = 0;
+        let mut bishop_squares = [false; 2];
+        for piece in self.data.pieces() {
+            let square = self.data.square_of_piece(piece).into_inner();
+            match self.data.piece_from_bit(piece) {
+                Piece::King => {}
+                Piece::Knight => minors += 1,
+                Piece::Bishop => {
+                    minors += 1;
+                    bishop_squares[usize::from((square / 8 + square % 8) % 2)] = true;
+                }
+                Piece::Pawn | Piece::Rook | Piece::Queen => return false,
+            }
+        }
+        minors <= 1 || (self.data.knights().empty() && !(bishop_squares[0] && bishop_squares[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrawReason, Outcome};
+    use crate::{board::Board, colour::Colour, test_support::find_move};
+
+    #[test]
+    fn outcomes() {
+        let outcome = |fen| Board::from_fen(fen).unwrap().outcome(&[]);
+
+        assert_eq!(outcome("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+        assert_eq!(outcome("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"), Some(Outcome::Win(Colour::White)));
+        assert_eq!(outcome("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), Some(Outcome::Draw(DrawReason::Stalemate)));
+        assert_eq!(outcome("4k3/8/8/8/8/8/8/4KN2 w - - 0 1"), Some(Outcome::Draw(DrawReason::InsufficientMaterial)));
+        assert_eq!(outcome("2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1"), Some(Outcome::Draw(DrawReason::InsufficientMaterial)));
+        assert_eq!(outcome("1b2k3/8/8/8/8/8/8/4KB2 w - - 0 1"), None);
+        assert_eq!(outcome("4k3/8/8/8/8/8/8/4KR2 w - - 99 80"), None);
+        assert_eq!(outcome("4k3/8/8/8/8/8/8/4KR2 w - - 100 80"), Some(Outcome::Draw(DrawReason::FiftyMoves)));
+    }
+
+    #[test]
+    fn capture_resets_repetition_window() {
+        let play = |board: &Board, history: &mut Vec<u64>, text: &str| {
+            let m = find_move(board, text);
+            history.push(board.repetition_key());
+            board.make(m)
+        };
+
+        // The kings shuffle back and forth, so the start position recurs.
+        let mut board = Board::from_fen("3rk3/8/8/8/8/8/8/3RK3 w - - 0 1").unwrap();
+        let mut history = Vec::new();
+        for text in ["e1e2", "e8e7", "e2e1", "e7e8"] {
+            board = play(&board, &mut history, text);
+        }
+        assert_eq!(board.halfmove_clock(), 4);
+        assert!(board.can_repeat_within(&history));
+
+        // Reaching the same position by a capture resets the clock, so the earlier occurrence doesn't count.
+        let captured = Board::from_fen("3rk3/8/8/8/8/8/8/3RK3 w - - 0 5").unwrap();
+        assert_eq!(captured.repetition_key(), board.repetition_key());
+        assert!(!captured.can_repeat_within(&history));
+    }
+
+    #[test]
+    fn wrong_bishop_rook_pawn() {
+        let draw = |fen| Board::from_fen(fen).unwrap().is_wrong_bishop_rook_pawn_draw();
+
+        // The dark-squared bishop can never drive Black's king out of the light a8 corner.
+        assert!(draw("k7/8/8/8/P7/8/8/2B1K3 w - - 0 1"));
+        // Likewise for Black's dark-squared bishop and the light h1 corner.
+        assert!(draw("4k3/8/8/2b5/7p/8/8/7K b - - 0 1"));
+        // The light-squared bishop covers a8, so White wins.
+        assert!(!draw("k7/8/8/8/P7/8/8/1B2K3 w - - 0 1"));
+        // Black's king is too far from the corner to get there in time.
+        assert!(!draw("8/8/8/8/P7/8/7k/2B1K3 w - - 0 1"));
+        // Pawns off the rook's file are not covered.
+        assert!(!draw("k7/8/8/8/1P6/8/8/2B1K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn is_stalemate() {
+        let stalemate = |fen| Board::from_fen(fen).unwrap().is_stalemate();
+        assert!(stalemate("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"));
+        assert!(!stalemate("7k/5Q2/6K1/8/8/8/8/8 w - - 0 1"));
+        assert!(!stalemate("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"));
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/4KR2 w - - 0 1").unwrap();
+        let mut history = Vec::new();
+
+        // Shuffle the kings back and forth; the start position recurs every four plies.
+        for (ply, text) in ["e1d1", "e8d8", "d1e1", "d8e8", "e1d1", "e8d8", "d1e1", "d8e8"].iter().enumerate() {
+            assert_eq!(board.outcome(&history), None, "ended early at ply {ply}");
+            let m = find_move(&board, text);
+
+            history.push(board.hash());
+            board = board.make(m);
+        }
+        assert_eq!(board.outcome(&history), Some(Outcome::Draw(DrawReason::Repetition)));
+    }
+}
@@ -98,13 +98,11 @@ impl Piecemask {
 
     /// Add a piece to a `Piecemask`.
     ///
-    /// Panics if adding a piece would give `colour` more than 16 pieces.
-    pub fn add_piece(&mut self, piece: Piece, colour: Colour) -> PieceIndex {
-        // SAFETY: a standard chess board has 32 pieces, of which 16 are white and 16 are black.
-        // Here we have a 32-bit integer, of which 16 bits are white and 16 are black.
-        // Thus, any position where one side has more than 16 pieces is by the rules of chess impossible to reach,
-        // and thus every time this gets called there will be at least one empty bit.
-        let piece_index = unsafe { (self.empty() & Bitlist::mask_from_colour(colour)).peek_nonzero() };
+    /// Returns `None` if adding a piece would give `colour` more than 16 pieces.
+    pub fn add_piece(&mut self, piece: Piece, colour: Colour) -> Option<PieceIndex> {
+        // A 32-bit integer has room for 16 white and 16 black pieces, which is all a game can reach,
+        // but a hand-written position may have more.
+        let piece_index = (self.empty() & Bitlist::mask_from_colour(colour)).peek()?;
         let yes = Bitlist::from(piece_index);
         let no = Bitlist::new();
 
@@ -121,7 +119,7 @@ impl Piecemask {
         self.nbk |= nbk;
         self.rqk |= rqk;
 
-        piece_index
+        Some(piece_index)
     }
 
     /// Remove a piece from a Piecemask.
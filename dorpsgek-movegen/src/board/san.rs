@@ -0,0 +1,162 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::{Board, CastlingSide};
+use crate::{
+    chessmove::{Move, MoveType},
+    piece::Piece,
+    square::{File, Rank},
+};
+use tinyvec::ArrayVec;
+
+/// The SAN letter of a piece; pawns have none.
+const fn piece_letter(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => "",
+        Piece::Knight => "N",
+        Piece::Bishop => "B",
+        Piece::Rook => "R",
+        Piece::Queen => "Q",
+        Piece::King => "K",
+    }
+}
+
+impl Board {
+    /// Render a legal move in Standard Algebraic Notation, including check and mate suffixes.
+    ///
+    /// # Panics
+    /// Panics if there is no piece on the source square of `m`.
+    #[must_use]
+    pub fn move_to_san(&self, m: Move) -> String {
+        let piece = self.data.piece_from_square(m.from).expect("no piece on source square");
+        let capture = matches!(m.kind, MoveType::Capture | MoveType::EnPassant | MoveType::CapturePromotion);
+
+        let mut san = match m.castle_side() {
+            Some(CastlingSide::King) => String::from("O-O"),
+            Some(CastlingSide::Queen) => String::from("O-O-O"),
+            None => {
+                let mut san = String::from(piece_letter(piece));
+                if piece == Piece::Pawn {
+                    if capture {
+                        san.push_str(&File::from(m.from).to_string());
+                    }
+                } else {
+                    san.push_str(&self.disambiguation(piece, m));
+                }
+                if capture {
+                    san.push('x');
+                }
+                san.push_str(&m.dest.to_string());
+                if let Some(prom) = m.prom {
+                    san.push('=');
+                    san.push_str(piece_letter(prom));
+                }
+                san
+            }
+        };
+
+        let after = self.make(m);
+        if after.in_check() {
+            san.push(if after.legal_move_count() == 0 { '#' } else { '+' });
+        }
+        san
+    }
+
+    /// Render a line of legal moves from this position in SAN, separated by spaces.
+    #[must_use]
+    pub fn line_to_san(&self, line: &[Move]) -> String {
+        let mut board = self.clone();
+        let mut moves = Vec::with_capacity(line.len());
+        for &m in line {
+            moves.push(board.move_to_san(m));
+            board = board.make(m);
+        }
+        moves.join(" ")
+    }
+
+    /// The file, rank or square needed to tell `m` apart from other moves of `piece` to the same square.
+    fn disambiguation(&self, piece: Piece, m: Move) -> String {
+        let mut v = ArrayVec::from([Move::default(); 256]);
+        v.set_len(0);
+        self.generate_piece_moves(piece, &mut v);
+
+        let rivals = v.iter().filter(|other| other.dest == m.dest && other.from != m.from);
+        let (mut ambiguous, mut same_file, mut same_rank) = (false, false, false);
+        for other in rivals {
+            ambiguous = true;
+            same_file |= u8::from(File::from(other.from)) == u8::from(File::from(m.from));
+            same_rank |= Rank::from(other.from) == Rank::from(m.from);
+        }
+
+        if !ambiguous {
+            String::new()
+        } else if !same_file {
+            File::from(m.from).to_string()
+        } else if !same_rank {
+            Rank::from(m.from).to_string()
+        } else {
+            m.from.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::Board, test_support::find_move};
+
+    fn san(fen: &str, text: &str) -> String {
+        let board = Board::from_fen(fen).unwrap();
+        board.move_to_san(find_move(&board, text))
+    }
+
+    #[test]
+    fn opening_line() {
+        let startpos = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let mut board = startpos.clone();
+        let mut line = Vec::new();
+        for text in &["e2e4", "e7e5", "g1f3"] {
+            let m = find_move(&board, text);
+            line.push(m);
+            board = board.make(m);
+        }
+        assert_eq!(startpos.line_to_san(&line), "e4 e5 Nf3");
+    }
+
+    #[test]
+    fn captures_castling_and_promotion() {
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(san(kiwipete, "e1g1"), "O-O");
+        assert_eq!(san(kiwipete, "e1c1"), "O-O-O");
+        assert_eq!(san(kiwipete, "d5e6"), "dxe6");
+        assert_eq!(san(kiwipete, "e2a6"), "Bxa6");
+        assert_eq!(san("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), "b8=Q+");
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+    }
+
+    #[test]
+    fn disambiguation() {
+        assert_eq!(san("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", "a1d1"), "Rad1");
+        assert_eq!(san("4k3/8/R7/8/8/8/8/R3K3 w - - 0 1", "a1a3"), "R1a3");
+        assert_eq!(san("4k3/8/8/8/8/Q1Q5/8/Q3K3 w - - 0 1", "a3b2"), "Qa3b2");
+    }
+
+    #[test]
+    fn check_and_mate() {
+        assert_eq!(san("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8+");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8#");
+    }
+}
@@ -0,0 +1,233 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::{Bitlist, Board, PieceIndex};
+use crate::{
+    chessmove::{Move, MoveType},
+    colour::Colour,
+    piece::Piece,
+    square::{Square, Square16x8},
+};
+use tinyvec::ArrayVec;
+
+impl Board {
+    /// Find the least valuable piece of `colour` in `attackers`.
+    fn least_valuable_attacker(&self, attackers: Bitlist, colour: Colour) -> Option<PieceIndex> {
+        let attackers = attackers & Bitlist::mask_from_colour(colour);
+        (attackers & self.data.pawns())
+            .peek()
+            .or_else(|| (attackers & self.data.knights()).peek())
+            .or_else(|| (attackers & self.data.bishops()).peek())
+            .or_else(|| (attackers & self.data.rooks()).peek())
+            .or_else(|| (attackers & self.data.queens()).peek())
+            .or_else(|| (attackers & self.data.kings()).peek())
+    }
+
+    /// Returns true if `attacker` is `colour`'s king and the other side still has `attackers` left,
+    /// so capturing would move into check. The king is always the last attacker chosen, so this ends the exchange.
+    fn king_cannot_capture(&self, attacker: PieceIndex, attackers: Bitlist, colour: Colour) -> bool {
+        self.data.piece_from_bit(attacker) == Piece::King && !(attackers & Bitlist::mask_from_colour(!colour)).empty()
+    }
+
+    /// Find a slider hidden behind a piece on `square` that attacks `target` once that piece moves.
+    fn xray_attacker(&self, target: Square, square: Square, used: Bitlist) -> Option<PieceIndex> {
+        let direction = target.direction(square)?;
+        for square in Square16x8::from_square(square).ray_attacks(direction) {
+            if let Some(index) = self.data.piece_index(square) {
+                if used.contains(Bitlist::from(index)) {
+                    continue;
+                }
+                return match self.data.piece_from_bit(index) {
+                    Piece::Bishop | Piece::Rook | Piece::Queen
+                        if direction.valid_for_slider(self.data.piece_from_bit(index)) =>
+                    {
+                        Some(index)
+                    }
+                    _ => None,
+                };
+            }
+        }
+        None
+    }
+
+    /// Statically evaluate the exchange of pieces that a move starts on its destination square.
+    ///
+    /// The result is the material the side to move can expect to gain from the move, assuming both
+    /// sides recapture with their least valuable piece for as long as it is profitable.
+    /// The king only recaptures onto a square the other side no longer attacks.
+    /// Pins are not taken into account.
+    ///
+    /// # Panics
+    /// Panics if there is no piece on the move's source square.
+    #[must_use]
+    pub fn see(&self, m: Move) -> i32 {
+        let target = m.dest;
+        let mover = self.data.piece_index(m.from).expect("no piece to move");
+
+        let victim = match m.kind {
+            MoveType::EnPassant => Some(Piece::Pawn),
+            _ => self.data.piece_from_square(target),
+        };
+
+        let mut gain: ArrayVec<[i32; 32]> = ArrayVec::new();
+        let mut on_square = self.data.piece_from_bit(mover);
+        let mut first = victim.map_or(0, Piece::value);
+        if let Some(prom) = m.prom {
+            first += prom.value() - Piece::Pawn.value();
+            on_square = prom;
+        }
+        gain.push(first);
+
+        let mut used = Bitlist::from(mover);
+        let (white, black) = self.data.attackers_both(target);
+        let mut attackers = white | black;
+        if let Some(xray) = self.xray_attacker(target, m.from, used) {
+            attackers |= Bitlist::from(xray);
+        }
+        attackers &= !used;
+
+        let mut side = !self.side;
+        while let Some(attacker) = self.least_valuable_attacker(attackers, side) {
+            if self.king_cannot_capture(attacker, attackers, side) {
+                break;
+            }
+            gain.push(on_square.value() - gain[gain.len() - 1]);
+            on_square = self.data.piece_from_bit(attacker);
+
+            used |= Bitlist::from(attacker);
+            if let Some(xray) =
+                self.xray_attacker(target, self.data.square_of_piece(attacker), used)
+            {
+                attackers |= Bitlist::from(xray);
+            }
+            attackers &= !used;
+            side = !side;
+        }
+
+        // Each side may decline to continue the exchange if doing so would lose material.
+        for depth in (1..gain.len()).rev() {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        }
+
+        gain[0]
+    }
+
+    /// The captures on `square` when both sides take with their least valuable piece, stopping once the next
+    /// capture would lose material, as in `see`.
+    /// Each entry is the square and piece making the capture, and the material balance afterwards for the side to move.
+    /// The sequence is empty if `square` holds no enemy piece or the side to move shouldn't start the exchange.
+    /// Pins and promotions are not taken into account.
+    #[must_use]
+    pub fn exchange_sequence(&self, square: Square) -> Vec<(Square, Piece, i32)> {
+        let victim = match self.data.piece_index(square) {
+            Some(victim) if self.data.colour_from_square(square) == Some(!self.side) => victim,
+            _ => return Vec::new(),
+        };
+
+        // Every capture either side could make, and the value of the piece each one takes.
+        let mut captures = Vec::new();
+        let mut values = Vec::new();
+        let mut on_square = self.data.piece_from_bit(victim);
+        let mut used = Bitlist::new();
+        let (white, black) = self.data.attackers_both(square);
+        let mut attackers = white | black;
+        let mut side = self.side;
+        while let Some(attacker) = self.least_valuable_attacker(attackers, side) {
+            if self.king_cannot_capture(attacker, attackers, side) {
+                break;
+            }
+            let from = self.data.square_of_piece(attacker);
+            values.push(on_square.value());
+            on_square = self.data.piece_from_bit(attacker);
+            captures.push((from, on_square));
+
+            used |= Bitlist::from(attacker);
+            if let Some(xray) = self.xray_attacker(square, from, used) {
+                attackers |= Bitlist::from(xray);
+            }
+            attackers &= !used;
+            side = !side;
+        }
+
+        // Working backwards, each side's best result is to capture or stop, whichever gains more.
+        let mut best = vec![0; values.len() + 1];
+        for depth in (0..values.len()).rev() {
+            best[depth] = (values[depth] - best[depth + 1]).max(0);
+        }
+
+        let mut sequence = Vec::new();
+        let mut balance = 0;
+        for (depth, (&(from, piece), &value)) in captures.iter().zip(&values).enumerate() {
+            if value - best[depth + 1] < 0 {
+                break;
+            }
+            balance += if depth % 2 == 0 { value } else { -value };
+            sequence.push((from, piece, balance));
+        }
+        sequence
+    }
+
+    /// The value of the piece `m` captures less the value of the piece making it, or zero for non-captures.
+    /// A capture-promotion also counts its promotion gain.
+    ///
+    /// # Panics
+    /// Panics if there is no piece on `m.from`.
+    #[must_use]
+    pub fn capture_gain(&self, m: Move) -> i32 {
+        let victim = match m.kind {
+            // The captured pawn is beside the destination, not on it.
+            MoveType::EnPassant => Piece::Pawn,
+            MoveType::Capture | MoveType::CapturePromotion => match self.data.piece_from_square(m.dest) {
+                Some(victim) => victim,
+                None => return 0,
+            },
+            MoveType::Normal | MoveType::DoublePush | MoveType::Castle | MoveType::Promotion => return 0,
+        };
+        let attacker = self.data.piece_from_square(m.from).expect("no piece to move");
+        victim.value() - attacker.value() + m.promotion_gain()
+    }
+
+    /// Returns true if the static exchange evaluation of a move is at least `threshold`.
+    #[must_use]
+    pub fn see_ge(&self, m: Move, threshold: i32) -> bool {
+        self.see(m) >= threshold
+    }
+
+    /// Returns true if `m` is a capture that wins material once the exchange on its square plays out.
+    #[must_use]
+    pub fn capture_wins_material(&self, m: Move) -> bool {
+        m.is_capture() && self.see_ge(m, 1)
+    }
+
+    /// Returns true if the side to move is not in check and has no captures that win material.
+    #[must_use]
+    pub fn is_quiet(&self) -> bool {
+        if self.in_check() {
+            return false;
+        }
+
+        let mut quiet = true;
+        self.generate_captures_incremental(|m| {
+            if self.see_ge(m, 1) {
+                quiet = false;
+                return false;
+            }
+            true
+        });
+        quiet
+    }
+}
@@ -0,0 +1,165 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::Display;
+
+use super::{king_start, rook_start, Board, CastlingSide};
+use crate::{
+    colour::Colour,
+    piece::Piece,
+    square::{Rank, Square},
+};
+
+/// A reason a position is not legal.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PositionError {
+    /// A side does not have exactly one king.
+    KingCount(Colour),
+    /// A side has more than the 16 pieces a board can hold.
+    TooManyPieces(Colour),
+    /// A pawn is on the first or eighth rank.
+    PawnOnBackRank(Square),
+    /// A castling right is set without the king and rook on their starting squares.
+    CastlingRights,
+    /// The side not to move is in check.
+    OpponentInCheck,
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let colour = |colour| match colour {
+            Colour::White => "white",
+            Colour::Black => "black",
+        };
+
+        match self {
+            Self::KingCount(c) => write!(f, "{} does not have exactly one king", colour(*c)),
+            Self::TooManyPieces(c) => write!(f, "{} has more than 16 pieces", colour(*c)),
+            Self::PawnOnBackRank(square) => write!(f, "pawn on back rank square {square}"),
+            Self::CastlingRights => write!(f, "castling rights without king and rook in place"),
+            Self::OpponentInCheck => write!(f, "side not to move is in check"),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl Board {
+    /// Check that the position is legal and internally consistent.
+    ///
+    /// # Errors
+    /// Returns the first problem found with the position.
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for colour in [Colour::White, Colour::Black] {
+            let pieces = self.data.pieces_of_colour(colour);
+            if pieces.count_ones() > 16 {
+                return Err(PositionError::TooManyPieces(colour));
+            }
+            if (pieces & self.data.kings()).count_ones() != 1 {
+                return Err(PositionError::KingCount(colour));
+            }
+        }
+
+        for piece in self.data.pawns() {
+            let square = self.data.square_of_piece(piece);
+            if matches!(Rank::from(square), Rank::One | Rank::Eight) {
+                return Err(PositionError::PawnOnBackRank(square));
+            }
+        }
+
+        let has = |square, piece, colour| {
+            self.data.piece_from_square(square) == Some(piece) && self.data.colour_from_square(square) == Some(colour)
+        };
+        for colour in [Colour::White, Colour::Black] {
+            for side in [CastlingSide::King, CastlingSide::Queen] {
+                if self.castle.can_castle(colour, side)
+                    && !(has(king_start(colour), Piece::King, colour) && has(rook_start(colour, side), Piece::Rook, colour))
+                {
+                    return Err(PositionError::CastlingRights);
+                }
+            }
+        }
+
+        if self.illegal() {
+            return Err(PositionError::OpponentInCheck);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PositionError;
+    use crate::{
+        board::{Board, BoardBuilder},
+        colour::Colour,
+        piece::Piece,
+        square::{File, Rank, Square},
+    };
+
+    fn validate(fen: &str) -> Result<(), PositionError> {
+        Board::from_fen(fen).unwrap().validate()
+    }
+
+    #[test]
+    fn legal_positions() {
+        assert_eq!(validate("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Ok(()));
+        assert_eq!(validate("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), Ok(()));
+    }
+
+    #[test]
+    fn king_count() {
+        assert_eq!(validate("8/8/8/8/8/8/8/4K3 w - - 0 1"), Err(PositionError::KingCount(Colour::Black)));
+        assert_eq!(validate("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"), Err(PositionError::KingCount(Colour::White)));
+    }
+
+    #[test]
+    fn pawn_on_back_rank() {
+        assert_eq!(
+            validate("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(PositionError::PawnOnBackRank(Square::from_rank_file(Rank::Eight, File::H)))
+        );
+    }
+
+    #[test]
+    fn castling_rights() {
+        assert_eq!(validate("4k3/8/8/8/8/8/8/4K3 w K - 0 1"), Err(PositionError::CastlingRights));
+        assert_eq!(validate("r3k3/8/8/8/8/8/8/4K3 w q - 0 1"), Ok(()));
+        assert_eq!(validate("r4k2/8/8/8/8/8/8/4K3 w q - 0 1"), Err(PositionError::CastlingRights));
+    }
+
+    #[test]
+    fn opponent_in_check() {
+        assert_eq!(validate("4k3/8/8/8/4R3/8/8/4K3 w - - 0 1"), Err(PositionError::OpponentInCheck));
+        assert_eq!(validate("4k3/8/8/8/4R3/8/8/4K3 b - - 0 1"), Ok(()));
+    }
+
+    #[test]
+    fn too_many_pieces() {
+        let mut builder = BoardBuilder::new()
+            .place(Square::from_rank_file(Rank::One, File::E), Piece::King, Colour::White)
+            .place(Square::from_rank_file(Rank::Eight, File::E), Piece::King, Colour::Black);
+        for file in [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H] {
+            builder = builder
+                .place(Square::from_rank_file(Rank::Two, file), Piece::Pawn, Colour::White)
+                .place(Square::from_rank_file(Rank::Three, file), Piece::Knight, Colour::White);
+        }
+
+        assert!(matches!(builder.try_build(), Err(PositionError::TooManyPieces(Colour::White))));
+    }
+}
@@ -0,0 +1,56 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::{colour::Colour, piece::Piece, square::{File, Square}};
+
+/// 768 piece-square keys, one side-to-move key, four castling keys and eight en-passant file keys.
+const KEY_COUNT: usize = 768 + 1 + 4 + 8;
+
+/// Zobrist keys, generated at compile time by a xorshift generator.
+static KEYS: [u64; KEY_COUNT] = {
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut index = 0;
+    while index < KEY_COUNT {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        keys[index] = state;
+        index += 1;
+    }
+    keys
+};
+
+/// The key for a piece of a colour on a square.
+pub fn piece(piece: Piece, colour: Colour, square: Square) -> u64 {
+    KEYS[(usize::from(colour) * 6 + piece as usize) * 64 + usize::from(square.into_inner())]
+}
+
+/// The key for black to move.
+pub fn side() -> u64 {
+    KEYS[768]
+}
+
+/// The key for a castling right, in `KQkq` order.
+pub fn castle(index: usize) -> u64 {
+    KEYS[769 + index]
+}
+
+/// The key for an en-passant file.
+pub fn ep(file: File) -> u64 {
+    KEYS[773 + usize::from(u8::from(file))]
+}
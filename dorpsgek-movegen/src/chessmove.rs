@@ -16,11 +16,13 @@
  */
 
 use crate::{
+    board::CastlingSide,
     piece::Piece,
     square::{File, Rank, Square},
 };
-use std::fmt::Display;
+use std::{convert::TryFrom, fmt::Display};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Copy, Clone, Default, PartialEq)]
 pub struct Move {
     pub from: Square,
@@ -75,9 +77,91 @@ impl Move {
             MoveType::Capture | MoveType::CapturePromotion | MoveType::EnPassant
         )
     }
+
+    /// Which side a castling move castles towards, judged by the king's destination file, or `None` for other moves.
+    #[must_use]
+    pub fn castle_side(&self) -> Option<CastlingSide> {
+        if !matches!(self.kind, MoveType::Castle) {
+            return None;
+        }
+        Some(if u8::from(File::from(self.dest)) >= u8::from(File::E) { CastlingSide::King } else { CastlingSide::Queen })
+    }
+
+    /// Returns true if this promotes to anything but a queen.
+    #[must_use]
+    pub const fn is_underpromotion(&self) -> bool {
+        matches!(self.prom, Some(Piece::Knight | Piece::Bishop | Piece::Rook))
+    }
+
+    /// Pack the move into 16 bits, for compact tables: 6 bits each for the origin and destination squares, then 4 for
+    /// the move type and promotion piece together.
+    #[must_use]
+    pub fn pack(self) -> u16 {
+        let promotion = || self.prom.map_or(0, |piece| piece as u16 - Piece::Knight as u16);
+        let code = match self.kind {
+            MoveType::Normal => 0,
+            MoveType::Capture => 1,
+            MoveType::Castle => 2,
+            MoveType::DoublePush => 3,
+            MoveType::EnPassant => 4,
+            MoveType::Promotion => 5 + promotion(),
+            MoveType::CapturePromotion => 9 + promotion(),
+        };
+        u16::from(self.from.into_inner()) | u16::from(self.dest.into_inner()) << 6 | code << 12
+    }
+
+    /// Recover a move from `pack`, or `None` if `packed` didn't come from it.
+    #[must_use]
+    pub fn unpack(packed: u16) -> Option<Self> {
+        const PROMOTIONS: [Piece; 4] = [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen];
+        #[allow(clippy::cast_possible_truncation)]
+        let square = |bits: u16| Square::try_from((bits & 63) as u8).ok();
+        let (kind, prom) = match packed >> 12 {
+            0 => (MoveType::Normal, None),
+            1 => (MoveType::Capture, None),
+            2 => (MoveType::Castle, None),
+            3 => (MoveType::DoublePush, None),
+            4 => (MoveType::EnPassant, None),
+            code @ 5..=8 => (MoveType::Promotion, Some(PROMOTIONS[usize::from(code - 5)])),
+            code @ 9..=12 => (MoveType::CapturePromotion, Some(PROMOTIONS[usize::from(code - 9)])),
+            _ => return None,
+        };
+        Some(Self::new(square(packed)?, square(packed >> 6)?, kind, prom))
+    }
+
+    /// The material gained by promoting: the promoted piece's value less a pawn's, or zero for other moves.
+    #[must_use]
+    pub const fn promotion_gain(&self) -> i32 {
+        match self.prom {
+            Some(piece) => piece.value() - Piece::Pawn.value(),
+            None => 0,
+        }
+    }
+}
+
+/// A move with the type of the piece making it, so consumers needn't look the piece up on the board afterwards.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, PartialEq)]
+pub struct AnnotatedMove {
+    pub m: Move,
+    /// The piece on the origin square; for a promotion, that is the pawn.
+    pub piece: Piece,
+}
+
+impl Default for AnnotatedMove {
+    fn default() -> Self {
+        Self { m: Move::default(), piece: Piece::Pawn }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+impl Display for AnnotatedMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.m.fmt(f)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MoveType {
     Normal,
     Capture,
@@ -93,3 +177,87 @@ impl Default for MoveType {
         Self::Normal
     }
 }
+
+impl MoveType {
+    /// Returns true if no sequence of moves can undo a move of this type.
+    /// Pawn moves of type `Normal` are also irreversible; see `Board::is_irreversible`.
+    #[must_use]
+    pub const fn is_irreversible(self) -> bool {
+        !matches!(self, Self::Normal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Move, MoveType};
+    use crate::{
+        board::CastlingSide,
+        piece::Piece,
+        square::{File, Rank, Square},
+        Board,
+    };
+    use tinyvec::ArrayVec;
+
+    #[test]
+    fn is_underpromotion() {
+        let e7 = Square::from_rank_file(Rank::Seven, File::E);
+        let e8 = Square::from_rank_file(Rank::Eight, File::E);
+        let promote = |piece| Move::new(e7, e8, MoveType::Promotion, Some(piece));
+
+        assert!(!promote(Piece::Queen).is_underpromotion());
+        assert!(promote(Piece::Knight).is_underpromotion());
+        assert!(promote(Piece::Bishop).is_underpromotion());
+        assert!(promote(Piece::Rook).is_underpromotion());
+        assert!(!Move::new(e7, e8, MoveType::Normal, None).is_underpromotion());
+    }
+
+    #[test]
+    fn castle_side() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut list = ArrayVec::from([Move::default(); 256]);
+        list.set_len(0);
+        board.generate(&mut list);
+
+        let side = |text: &str| list.iter().find(|m| m.to_string() == text).unwrap().castle_side();
+        assert_eq!(side("e1g1"), Some(CastlingSide::King));
+        assert_eq!(side("e1c1"), Some(CastlingSide::Queen));
+        assert_eq!(side("e1f1"), None);
+        assert_eq!(side("h1h8"), None);
+    }
+
+    #[test]
+    fn pack_round_trips() {
+        let mut moves = 0;
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mut list = ArrayVec::from([Move::default(); 256]);
+            list.set_len(0);
+            board.generate(&mut list);
+            for m in list {
+                let unpacked = Move::unpack(m.pack()).unwrap();
+                assert!(unpacked == m, "{} in {}", m, fen);
+                assert_eq!(unpacked.kind, m.kind);
+                moves += 1;
+            }
+        }
+        assert!(moves > 100);
+
+        // The top type codes are unused.
+        assert!(Move::unpack(0xF000).is_none());
+    }
+
+    #[test]
+    fn promotion_gain() {
+        let e7 = Square::from_rank_file(Rank::Seven, File::E);
+        let e8 = Square::from_rank_file(Rank::Eight, File::E);
+
+        assert_eq!(Move::new(e7, e8, MoveType::Promotion, Some(Piece::Queen)).promotion_gain(), 800);
+        assert_eq!(Move::new(e7, e8, MoveType::Promotion, Some(Piece::Knight)).promotion_gain(), 200);
+        assert_eq!(Move::new(e7, e8, MoveType::Normal, None).promotion_gain(), 0);
+    }
+}
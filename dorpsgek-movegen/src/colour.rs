@@ -15,10 +15,11 @@
  *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::ops::Not;
+use std::{fmt::Display, ops::Not};
 
 /// A piece colour.
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Colour {
     /// White pieces.
     White,
@@ -36,6 +37,15 @@ impl From<Colour> for usize {
     }
 }
 
+impl Display for Colour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::White => write!(f, "White"),
+            Self::Black => write!(f, "Black"),
+        }
+    }
+}
+
 impl Not for Colour {
     type Output = Self;
 
@@ -26,12 +26,18 @@ mod chessmove;
 mod colour;
 mod piece;
 mod square;
+#[cfg(test)]
+mod test_support;
 
-pub use board::{Board, PieceIndex};
-pub use chessmove::{Move, MoveType};
+pub use board::{
+    king_castle_dest, king_start, rook_castle_dest, rook_start, Board, BoardBuilder, CastlingRights, CastlingSide,
+    DrawReason, MoveDiff, MoveInfo, Outcome, PieceIndex, PositionError,
+};
+pub use chessmove::{AnnotatedMove, Move, MoveType};
 pub use colour::Colour;
 pub use piece::Piece;
-pub use square::Square;
+pub use square::{Square, SquareColour};
+use std::time::{Duration, Instant};
 use tinyvec::ArrayVec;
 
 /// Count the number of legal chess positions after N moves.
@@ -41,11 +47,7 @@ pub fn perft(board: &Board, depth: u32) -> u64 {
     if depth == 0 {
         1
     } else if depth == 1 {
-        let moves: [Move; 256] = [Move::default(); 256];
-        let mut moves = ArrayVec::from(moves);
-        moves.set_len(0);
-        board.generate(&mut moves);
-        moves.len() as u64
+        board.legal_move_count() as u64
     } else {
         let moves: [Move; 256] = [Move::default(); 256];
         let mut moves = ArrayVec::from(moves);
@@ -61,9 +63,30 @@ pub fn perft(board: &Board, depth: u32) -> u64 {
     }
 }
 
+/// Run `perft`, also returning how long it took and the nodes per second, for comparing speed across changes
+/// and machines.
+#[must_use]
+pub fn perft_timed(board: &Board, depth: u32) -> (u64, Duration, f64) {
+    let start = Instant::now();
+    let nodes = perft(board, depth);
+    let elapsed = start.elapsed();
+    #[allow(clippy::cast_precision_loss)]
+    let nps = nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    (nodes, elapsed, nps)
+}
+
 #[cfg(test)]
 mod perft {
-    use crate::{perft, Board};
+    use crate::{perft, perft_timed, Board};
+
+    #[test]
+    fn perft_timed_matches_perft() {
+        let startpos =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let (nodes, _, nps) = perft_timed(&startpos, 3);
+        assert_eq!(nodes, perft(&startpos, 3));
+        assert!(nps > 0.0);
+    }
 
     #[test]
     fn perft_test1() {
@@ -1452,4 +1475,66 @@ mod perft {
         assert_eq!(perft(&startpos, 5), 3_605_103);
         assert_eq!(perft(&startpos, 6), 71_179_139);
     }
+
+    #[test]
+    fn perft_test127() {
+        // Many checks, including discovered and double checks, exercising the incremental checker tracking.
+        let startpos =
+            Board::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
+                .unwrap();
+        assert_eq!(perft(&startpos, 1), 6);
+        assert_eq!(perft(&startpos, 2), 264);
+        assert_eq!(perft(&startpos, 3), 9467);
+        assert_eq!(perft(&startpos, 4), 422_333);
+        assert_eq!(perft(&startpos, 5), 15_833_292);
+    }
+
+    #[test]
+    fn perft_test128() {
+        // An open middlegame with many quiet piece moves, exercising the quiet move loop.
+        let startpos =
+            Board::from_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10")
+                .unwrap();
+        assert_eq!(perft(&startpos, 1), 46);
+        assert_eq!(perft(&startpos, 2), 2079);
+        assert_eq!(perft(&startpos, 3), 89_890);
+        assert_eq!(perft(&startpos, 4), 3_894_594);
+    }
+
+    #[test]
+    fn perft_test129() {
+        // Position 4 with colours reversed: lots of checks, king moves and captures of checking pieces.
+        let startpos =
+            Board::from_fen("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1")
+                .unwrap();
+        assert_eq!(perft(&startpos, 1), 6);
+        assert_eq!(perft(&startpos, 2), 264);
+        assert_eq!(perft(&startpos, 3), 9467);
+        assert_eq!(perft(&startpos, 4), 422_333);
+    }
+
+    #[test]
+    fn perft_test130() {
+        // Exposed kings checked by sliders, whose attacks continue past the king, including a double check.
+        let startpos = Board::from_fen("4k3/8/8/8/8/8/8/r3K2R w K - 0 1").unwrap();
+        assert_eq!(perft(&startpos, 1), 3);
+        assert_eq!(perft(&startpos, 2), 57);
+        assert_eq!(perft(&startpos, 3), 951);
+        assert_eq!(perft(&startpos, 4), 15899);
+        assert_eq!(perft(&startpos, 5), 270_155);
+
+        let startpos = Board::from_fen("4k3/8/8/8/1b6/8/8/r3K3 w - - 0 1").unwrap();
+        assert_eq!(perft(&startpos, 1), 2);
+        assert_eq!(perft(&startpos, 2), 56);
+        assert_eq!(perft(&startpos, 3), 267);
+        assert_eq!(perft(&startpos, 4), 7052);
+        assert_eq!(perft(&startpos, 5), 40403);
+
+        let startpos = Board::from_fen("8/8/3k4/8/8/3K4/8/q6Q w - - 0 1").unwrap();
+        assert_eq!(perft(&startpos, 1), 27);
+        assert_eq!(perft(&startpos, 2), 598);
+        assert_eq!(perft(&startpos, 3), 11644);
+        assert_eq!(perft(&startpos, 4), 240_196);
+        assert_eq!(perft(&startpos, 5), 4_641_430);
+    }
 }
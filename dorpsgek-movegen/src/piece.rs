@@ -15,6 +15,9 @@
  *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::fmt::Display;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Piece {
     Pawn,
@@ -38,3 +41,50 @@ impl From<Piece> for usize {
         }
     }
 }
+
+/// Pieces need a default to be stored in an `ArrayVec`; the value is arbitrary.
+impl Default for Piece {
+    fn default() -> Self {
+        Self::Pawn
+    }
+}
+
+impl Display for Piece {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Pawn => "Pawn",
+            Self::Knight => "Knight",
+            Self::Bishop => "Bishop",
+            Self::Rook => "Rook",
+            Self::Queen => "Queen",
+            Self::King => "King",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Piece {
+    /// The nominal material value of a piece, in centipawns.
+    #[must_use]
+    pub const fn value(self) -> i32 {
+        match self {
+            Self::Pawn => 100,
+            Self::Knight | Self::Bishop => 300,
+            Self::Rook => 500,
+            Self::Queen => 900,
+            Self::King => 20_000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Piece;
+    use crate::colour::Colour;
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", Piece::Knight), "Knight");
+        assert_eq!(format!("{} {}", Colour::Black, Piece::King), "Black King");
+    }
+}
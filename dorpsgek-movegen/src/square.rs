@@ -21,6 +21,7 @@ use std::{
     fmt::{Debug, Display},
     num::NonZeroU8,
 };
+use tinyvec::ArrayVec;
 
 const DIRECTIONS: [Option<Direction>; 240] = [
     Some(Direction::SouthWest),
@@ -265,6 +266,13 @@ const DIRECTIONS: [Option<Direction>; 240] = [
     None,
 ];
 
+/// The colour of a square on the board.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SquareColour {
+    Light,
+    Dark,
+}
+
 /// A chessboard rank.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Rank {
@@ -354,6 +362,13 @@ impl Rank {
         }
     }
 
+    /// The rank `ranks` steps north, or `None` if that is off the board.
+    #[must_use]
+    pub fn offset(self, ranks: i8) -> Option<Self> {
+        let rank = i8::try_from(u8::from(self)).ok()?.checked_add(ranks)?;
+        Self::try_from(u8::try_from(rank).ok()?).ok()
+    }
+
     pub fn is_relative_fourth(self, colour: Colour) -> bool {
         match colour {
             Colour::White => self == Self::Four,
@@ -456,6 +471,13 @@ impl File {
             Self::H => Some(Self::G),
         }
     }
+
+    /// The file `files` steps east, or `None` if that is off the board.
+    #[must_use]
+    pub fn offset(self, files: i8) -> Option<Self> {
+        let file = i8::try_from(u8::from(self)).ok()?.checked_add(files)?;
+        Self::try_from(u8::try_from(file).ok()?).ok()
+    }
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -540,6 +562,22 @@ impl Debug for Square {
     }
 }
 
+/// Squares are serialized as their index, `0` for a1 to `63` for h8.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Square {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.into_inner())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Square {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = u8::deserialize(deserializer)?;
+        Self::try_from(index).map_err(|()| serde::de::Error::custom(format!("square index {index} out of range")))
+    }
+}
+
 impl From<Square> for Rank {
     fn from(square: Square) -> Self {
         // This is an exhaustive match, so the unreachable! really is unreachable.
@@ -576,6 +614,18 @@ impl From<Square> for File {
     }
 }
 
+impl From<(Rank, File)> for Square {
+    fn from((rank, file): (Rank, File)) -> Self {
+        Self::from_rank_file(rank, file)
+    }
+}
+
+impl From<(File, Rank)> for Square {
+    fn from((file, rank): (File, Rank)) -> Self {
+        Self::from_rank_file(rank, file)
+    }
+}
+
 impl TryFrom<u8> for Square {
     type Error = ();
 
@@ -590,12 +640,15 @@ impl TryFrom<u8> for Square {
 impl Square {
     /// Construct a `Square` from a `Rank` and `File`.
     #[must_use]
-    pub fn from_rank_file(rank: Rank, file: File) -> Self {
-        let rank = u8::from(rank);
-        let file = u8::from(file);
-        // SAFETY: the "plus one" ensures this will never be zero.
-        let square = unsafe { NonZeroU8::new_unchecked((8 * rank + file) + 1) };
-        Self(square)
+    pub const fn from_rank_file(rank: Rank, file: File) -> Self {
+        // SAFETY: ranks and files are numbered 0-7 in declaration order, so the index is in range.
+        unsafe { Self::from_u8_unchecked(8 * rank as u8 + file as u8) }
+    }
+
+    /// Construct a `Square` from a `File` and `Rank`, in the order of its name: `Square::new(File::E, Rank::Four)` is e4.
+    #[must_use]
+    pub const fn new(file: File, rank: Rank) -> Self {
+        Self::from_rank_file(rank, file)
     }
 
     /// Construct a `Square` directly from a `u8`.
@@ -624,12 +677,33 @@ impl Square {
         unsafe { *DIRECTIONS.get_unchecked(from.vector(dest)) }
     }
 
+    /// Return the squares strictly between two squares on a line, or none if they aren't aligned.
+    #[must_use]
+    pub fn between(self, other: Self) -> ArrayVec<[Self; 6]> {
+        let mut squares = ArrayVec::new();
+        if let Some(direction) = self.direction(other) {
+            for square in Square16x8::from_square(self).ray_attacks(direction) {
+                if square == other {
+                    break;
+                }
+                squares.push(square);
+            }
+        }
+        squares
+    }
+
     /// Return the `Square` in a given `Direction`, if one exists.
     #[must_use]
     pub fn travel(self, direction: Direction) -> Option<Self> {
         Square16x8::from_square(self).add_dir(direction).to_square()
     }
 
+    /// Return the `Square` `files` east and `ranks` north of this one, if it is on the board.
+    #[must_use]
+    pub fn offset(self, files: i8, ranks: i8) -> Option<Self> {
+        Some(Self::from_rank_file(Rank::from(self).offset(ranks)?, File::from(self).offset(files)?))
+    }
+
     #[must_use]
     pub fn north(self) -> Option<Self> {
         self.travel(Direction::North)
@@ -715,6 +789,43 @@ impl Square {
     pub const fn flip(self) -> Self {
         unsafe { Self::from_u8_unchecked(self.into_inner() ^ 56) }
     }
+
+    /// The square as seen from `colour`'s side of the board, so Black squares can be treated as White ones.
+    #[must_use]
+    pub const fn relative_to(self, colour: Colour) -> Self {
+        match colour {
+            Colour::White => self,
+            Colour::Black => self.flip(),
+        }
+    }
+
+    /// The number of pawn steps from this square to `colour`'s promotion rank.
+    #[must_use]
+    pub const fn steps_to_promotion(self, colour: Colour) -> u8 {
+        7 - self.relative_to(colour).into_inner() / 8
+    }
+
+    /// Whether this is a light or dark square; a1 is dark.
+    #[must_use]
+    pub const fn square_colour(self) -> SquareColour {
+        if (self.into_inner() / 8 + self.into_inner() % 8) & 1 == 0 {
+            SquareColour::Dark
+        } else {
+            SquareColour::Light
+        }
+    }
+
+    /// The number of king moves between this square and `other`.
+    #[must_use]
+    pub const fn chebyshev_distance(self, other: Self) -> u8 {
+        let files = (self.into_inner() % 8).abs_diff(other.into_inner() % 8);
+        let ranks = (self.into_inner() / 8).abs_diff(other.into_inner() / 8);
+        if files > ranks {
+            files
+        } else {
+            ranks
+        }
+    }
 }
 
 /// A chess direction.
@@ -801,6 +912,53 @@ impl Direction {
         VECTORS[self as usize]
     }
 
+    /// All sixteen directions, in clockwise order from north.
+    pub const fn all() -> [Self; 16] {
+        [
+            Self::North,
+            Self::NorthNorthEast,
+            Self::NorthEast,
+            Self::EastNorthEast,
+            Self::East,
+            Self::EastSouthEast,
+            Self::SouthEast,
+            Self::SouthSouthEast,
+            Self::South,
+            Self::SouthSouthWest,
+            Self::SouthWest,
+            Self::WestSouthWest,
+            Self::West,
+            Self::WestNorthWest,
+            Self::NorthWest,
+            Self::NorthNorthWest,
+        ]
+    }
+
+    /// The directions a piece attacks in: it slides along them if it is a bishop, rook or queen, and steps once otherwise.
+    /// Pawn directions are white's; black pawns attack in the opposite directions.
+    pub const fn for_piece(piece: Piece) -> &'static [Self] {
+        use Direction::{
+            East, EastNorthEast, EastSouthEast, North, NorthEast, NorthNorthEast, NorthNorthWest, NorthWest, South,
+            SouthEast, SouthSouthEast, SouthSouthWest, SouthWest, West, WestNorthWest, WestSouthWest,
+        };
+        match piece {
+            Piece::Pawn => &[NorthEast, NorthWest],
+            Piece::Knight => &[
+                NorthNorthEast,
+                EastNorthEast,
+                EastSouthEast,
+                SouthSouthEast,
+                SouthSouthWest,
+                WestSouthWest,
+                WestNorthWest,
+                NorthNorthWest,
+            ],
+            Piece::Bishop => &[NorthEast, SouthEast, SouthWest, NorthWest],
+            Piece::Rook => &[North, East, South, West],
+            Piece::Queen | Piece::King => &[North, NorthEast, East, SouthEast, South, SouthWest, West, NorthWest],
+        }
+    }
+
     pub fn valid_for_slider(self, piece: Piece) -> bool {
         match piece {
             Piece::Bishop => self.diagonal(),
@@ -912,3 +1070,131 @@ impl Iterator for KingIter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, File, Rank, Square, SquareColour};
+    use crate::{colour::Colour, piece::Piece};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn directions_by_piece() {
+        let bishop = Direction::for_piece(Piece::Bishop);
+        assert_eq!(bishop.len(), 4);
+        assert!(bishop.iter().all(|dir| dir.diagonal()));
+
+        let rook = Direction::for_piece(Piece::Rook);
+        assert_eq!(rook.len(), 4);
+        assert!(rook.iter().all(|dir| dir.orthogonal()));
+
+        // Each direction appears once in the full list, in discriminant order.
+        for (index, dir) in Direction::all().iter().enumerate() {
+            assert_eq!(*dir as usize, index);
+        }
+    }
+
+    #[test]
+    fn constructors_agree() {
+        const E4: Square = Square::new(File::E, Rank::Four);
+        assert_eq!(E4.to_string(), "e4");
+
+        for index in 0..64 {
+            let square = Square::try_from(index).unwrap();
+            let (rank, file) = (Rank::from(square), File::from(square));
+            assert_eq!(Square::from_rank_file(rank, file), square);
+            assert_eq!(Square::new(file, rank), square);
+            assert_eq!(Square::from((rank, file)), square);
+            assert_eq!(Square::from((file, rank)), square);
+        }
+    }
+
+    #[test]
+    fn between() {
+        let a1 = Square::from_rank_file(Rank::One, File::A);
+        let a2 = Square::from_rank_file(Rank::Two, File::A);
+        let a3 = Square::from_rank_file(Rank::Three, File::A);
+        let a4 = Square::from_rank_file(Rank::Four, File::A);
+        let b3 = Square::from_rank_file(Rank::Three, File::B);
+        let h8 = Square::from_rank_file(Rank::Eight, File::H);
+
+        assert_eq!(a1.between(a4).as_slice(), &[a2, a3]);
+        assert_eq!(a4.between(a1).as_slice(), &[a3, a2]);
+        assert_eq!(
+            a1.between(h8).as_slice(),
+            &[
+                Square::from_rank_file(Rank::Two, File::B),
+                Square::from_rank_file(Rank::Three, File::C),
+                Square::from_rank_file(Rank::Four, File::D),
+                Square::from_rank_file(Rank::Five, File::E),
+                Square::from_rank_file(Rank::Six, File::F),
+                Square::from_rank_file(Rank::Seven, File::G),
+            ]
+        );
+        assert!(a1.between(a2).is_empty());
+        assert!(a1.between(a1).is_empty());
+        assert!(a1.between(b3).is_empty());
+    }
+
+    #[test]
+    fn relative_geometry() {
+        let e5 = Square::from_rank_file(Rank::Five, File::E);
+        let e4 = Square::from_rank_file(Rank::Four, File::E);
+
+        assert_eq!(e5.relative_to(Colour::White), e5);
+        assert_eq!(e5.relative_to(Colour::Black), e4);
+
+        assert_eq!(e5.steps_to_promotion(Colour::White), 3);
+        assert_eq!(e4.steps_to_promotion(Colour::Black), 3);
+        assert_eq!(Square::from_rank_file(Rank::Seven, File::A).steps_to_promotion(Colour::White), 1);
+        assert_eq!(Square::from_rank_file(Rank::Two, File::A).steps_to_promotion(Colour::Black), 1);
+
+        assert_eq!(e5.chebyshev_distance(e4), 1);
+        assert_eq!(e5.chebyshev_distance(e5), 0);
+        assert_eq!(Square::from_rank_file(Rank::One, File::A).chebyshev_distance(Square::from_rank_file(Rank::Two, File::H)), 7);
+    }
+
+    #[test]
+    fn square_colour() {
+        assert_eq!(Square::from_rank_file(Rank::One, File::A).square_colour(), SquareColour::Dark);
+        assert_eq!(Square::from_rank_file(Rank::One, File::H).square_colour(), SquareColour::Light);
+        assert_eq!(Square::from_rank_file(Rank::Eight, File::H).square_colour(), SquareColour::Dark);
+
+        // Each side's bishops start on opposite colours.
+        for rank in [Rank::One, Rank::Eight] {
+            let c = Square::from_rank_file(rank, File::C).square_colour();
+            let f = Square::from_rank_file(rank, File::F).square_colour();
+            assert_ne!(c, f);
+        }
+    }
+
+    #[test]
+    fn offset() {
+        let e4 = Square::from_rank_file(Rank::Four, File::E);
+        let a1 = Square::from_rank_file(Rank::One, File::A);
+
+        assert_eq!(e4.offset(1, 2), Some(Square::from_rank_file(Rank::Six, File::F)));
+        assert_eq!(e4.offset(0, 0), Some(e4));
+        assert_eq!(a1.offset(-1, 0), None);
+        assert_eq!(a1.offset(0, -1), None);
+        assert_eq!(a1.offset(7, 7), Some(Square::from_rank_file(Rank::Eight, File::H)));
+        assert_eq!(a1.offset(8, 0), None);
+        assert_eq!(a1.offset(i8::MAX, i8::MIN), None);
+
+        let directions = [
+            (Direction::North, 0, 1),
+            (Direction::NorthEast, 1, 1),
+            (Direction::East, 1, 0),
+            (Direction::SouthEast, 1, -1),
+            (Direction::South, 0, -1),
+            (Direction::SouthWest, -1, -1),
+            (Direction::West, -1, 0),
+            (Direction::NorthWest, -1, 1),
+        ];
+        for square in 0..64 {
+            let square = Square::try_from(square).unwrap();
+            for &(direction, files, ranks) in &directions {
+                assert_eq!(square.offset(files, ranks), square.travel(direction), "{square} {direction:?}");
+            }
+        }
+    }
+}
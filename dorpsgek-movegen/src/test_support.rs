@@ -0,0 +1,37 @@
+/*
+ *   This file is part of Dorpsgek.
+ *
+ *   Dorpsgek is free software: you can redistribute it and/or modify
+ *   it under the terms of the GNU General Public License as published by
+ *   the Free Software Foundation, either version 3 of the License, or
+ *   (at your option) any later version.
+ *
+ *   Dorpsgek is distributed in the hope that it will be useful,
+ *   but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *   GNU General Public License for more details.
+ *
+ *   You should have received a copy of the GNU General Public License
+ *   along with Dorpsgek.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Helpers shared by the unit tests.
+
+use crate::{board::Board, chessmove::Move};
+use tinyvec::ArrayVec;
+
+/// The legal moves of `board`.
+pub fn legal_moves(board: &Board) -> ArrayVec<[Move; 256]> {
+    let mut moves = ArrayVec::from([Move::default(); 256]);
+    moves.set_len(0);
+    board.generate(&mut moves);
+    moves
+}
+
+/// The legal move of `board` written as `text` in coordinate notation.
+pub fn find_move(board: &Board, text: &str) -> Move {
+    legal_moves(board)
+        .into_iter()
+        .find(|m| m.to_string() == text)
+        .expect("move is not legal")
+}
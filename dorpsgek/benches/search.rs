@@ -1,6 +1,7 @@
-use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
 use dorpsgek_movegen::Board;
-use dorpsgek::Search;
+use dorpsgek::{ReplacementPolicy, Search, SearchOptions};
+use std::ops::RangeInclusive;
 use tinyvec::ArrayVec;
 
 pub fn search_bench(c: &mut Criterion) {
@@ -23,11 +24,12 @@ pub fn search_bench(c: &mut Criterion) {
 
     group.throughput(Throughput::Elements(nodes));
     group.bench_with_input("kiwipete-3", &kiwipete, |b, board| {
-        let mut s = Search::new();
         let mut pv = ArrayVec::new();
-        b.iter(|| {
-            s.search_root(board, 3, &mut pv);
-        })
+        b.iter_batched(
+            Search::new,
+            |mut s| s.search_root(board, 3, &mut pv),
+            BatchSize::LargeInput,
+        )
     });
 
     let nodes = {
@@ -39,18 +41,93 @@ pub fn search_bench(c: &mut Criterion) {
 
     group.throughput(Throughput::Elements(nodes));
     group.bench_with_input("kiwipete-4", &kiwipete, |b, board| {
-        let mut s = Search::new();
         let mut pv = ArrayVec::new();
-        b.iter(|| {
-            s.search_root(board, 4, &mut pv);
-        })
+        b.iter_batched(
+            Search::new,
+            |mut s| s.search_root(board, 4, &mut pv),
+            BatchSize::LargeInput,
+        )
     });
 
     group.finish();
 }
 
+/// Search kiwipete to each depth in `depths` in turn under each case's options, so the options can be compared.
+/// Criterion reports the nodes each case searches as its throughput.
+fn compare_options(c: &mut Criterion, group: &str, cases: &[(&str, SearchOptions)], depths: RangeInclusive<i32>) {
+    let kiwipete =
+        Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+
+    let mut group = c.benchmark_group(group);
+
+    group.sample_size(10);
+
+    let search = |s: &mut Search, board: &Board| {
+        let mut pv = ArrayVec::new();
+        for depth in depths.clone() {
+            s.search_root(board, depth, &mut pv);
+        }
+    };
+
+    for &(name, options) in cases {
+        let nodes = {
+            let mut s = Search::with_options(options);
+            search(&mut s, &kiwipete);
+            s.nodes() + s.qnodes()
+        };
+
+        group.throughput(Throughput::Elements(nodes));
+        group.bench_with_input(name, &kiwipete, |b, board| {
+            b.iter_batched(|| Search::with_options(options), |mut s| search(&mut s, board), BatchSize::LargeInput)
+        });
+    }
+
+    group.finish();
+}
+
+pub fn replacement_bench(c: &mut Criterion) {
+    // A deliberately small table, so that the replacement policy matters.
+    let policy = |replacement| SearchOptions { tt_size: 1 << 12, replacement, ..SearchOptions::default() };
+    compare_options(
+        c,
+        "replacement",
+        &[
+            ("always-replace", policy(ReplacementPolicy::AlwaysReplace)),
+            ("depth-preferred", policy(ReplacementPolicy::DepthPreferred)),
+            ("two-tier", policy(ReplacementPolicy::TwoTier)),
+        ],
+        1..=5,
+    );
+}
+
+pub fn countermove_bench(c: &mut Criterion) {
+    let countermoves = |countermoves| SearchOptions { countermoves, ..SearchOptions::default() };
+    compare_options(
+        c,
+        "countermoves",
+        &[("without-countermoves", countermoves(false)), ("with-countermoves", countermoves(true))],
+        1..=5,
+    );
+}
+
+pub fn late_move_pruning_bench(c: &mut Criterion) {
+    let lmp = |late_move_pruning| SearchOptions { late_move_pruning, ..SearchOptions::default() };
+    compare_options(c, "late-move-pruning", &[("without-lmp", lmp(false)), ("with-lmp", lmp(true))], 1..=6);
+}
+
+pub fn internal_iterative_deepening_bench(c: &mut Criterion) {
+    // A single search with an empty hash table, so no node starts with a hash move.
+    let iid = |internal_iterative_deepening| SearchOptions { internal_iterative_deepening, ..SearchOptions::default() };
+    compare_options(c, "internal-iterative-deepening", &[("without-iid", iid(false)), ("with-iid", iid(true))], 6..=6);
+}
+
 pub fn bench(c: &mut Criterion) {
     search_bench(c);
+    replacement_bench(c);
+    countermove_bench(c);
+    late_move_pruning_bench(c);
+    internal_iterative_deepening_bench(c);
 }
 
 criterion_group! {
@@ -11,6 +11,7 @@ fn main() {
     } else {
         fen
     }).unwrap();
+    let san = std::env::args().skip(2).any(|arg| arg == "--san");
 
     let mut s = Search::new();
     let start = Instant::now();
@@ -26,8 +27,12 @@ fn main() {
             now.as_millis() / 10,
             s.nodes() + s.qnodes()
         );
-        for m in pv {
-            print!("{} ", m);
+        if san {
+            print!("{}", board.line_to_san(&pv));
+        } else {
+            for m in pv {
+                print!("{} ", m);
+            }
         }
         println!();
     }
@@ -35,7 +35,7 @@ fn main() {
 
     for epoch in 0..500 {
         let tape = Tape::new();
-        let mut tune = Tune::new(&tape);
+        let mut tune = Tune::new_seeded(&tape, epoch as u64);
         tune.set_state(&tape, &weights, &m_t, &v_t);
 
         tune.tune(&tape, &boards, epoch);
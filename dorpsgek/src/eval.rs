@@ -1,6 +1,39 @@
 use std::convert::TryInto;
 
-use dorpsgek_movegen::{Board, Colour, Move, MoveType, Piece, Square};
+use dorpsgek_movegen::{Board, CastlingSide, Colour, Move, MoveType, Piece, Square};
+
+/// Evaluation units per pawn: every score in the evaluation and search is in centipawns.
+pub const CP_SCALE: i32 = 100;
+
+/// No legal position evaluates beyond this many centipawns. Nine queens and every other piece against a bare
+/// king is about 116 pawns of material, so this leaves room for the positional terms.
+pub const MAX_EVAL: i32 = 200 * CP_SCALE;
+
+/// The number of king moves from each square to the central four squares, so corners are furthest.
+#[rustfmt::skip]
+const CENTRE_DISTANCE: [i32; 64] = [
+    6, 5, 4, 3, 3, 4, 5, 6,
+    5, 4, 3, 2, 2, 3, 4, 5,
+    4, 3, 2, 1, 1, 2, 3, 4,
+    3, 2, 1, 0, 0, 1, 2, 3,
+    3, 2, 1, 0, 0, 1, 2, 3,
+    4, 3, 2, 1, 1, 2, 3, 4,
+    5, 4, 3, 2, 2, 3, 4, 5,
+    6, 5, 4, 3, 3, 4, 5, 6,
+];
+
+/// Bonus per step the lone king is from the centre.
+const MATING_EDGE: i32 = 30;
+/// Bonus per step the kings are closer than opposite corners.
+const MATING_PROXIMITY: i32 = 30;
+/// Bonus per safe square of space, per minor piece.
+const SPACE: i32 = 1;
+/// Bonus for a pawn defended by or beside a friendly pawn, by relative rank.
+const CONNECTED: [i32; 8] = [0, 2, 4, 6, 10, 16, 24, 0];
+/// Middlegame bonus per step a piece is closer to the enemy king than the far side of the board, by piece.
+const TROPISM: [i32; 6] = [0, 3, 2, 2, 4, 0];
+/// Positions known to be drawn despite the material have their score divided by this.
+const DRAW_SCALE: i32 = 16;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct EvalState {
@@ -18,8 +51,14 @@ impl EvalState {
         }
     }
 
+    /// The game phase, from 24 with every piece on the board down to 0 with only kings and pawns.
+    pub fn phase(&self) -> i32 {
+        self.phase.clamp(0, 24)
+    }
+
     pub fn get(&self, colour: Colour) -> i32 {
-        let score = ((self.pst_mg * self.phase) + (self.pst_eg * (24 - self.phase))) / 24;
+        let phase = self.phase();
+        let score = ((self.pst_mg * phase) + (self.pst_eg * (24 - phase))) / 24;
         if colour == Colour::White {
             score
         } else {
@@ -68,6 +107,10 @@ pub struct Eval {
     pub pst_mg: [[i32; 64]; 6],
     pub pst_eg: [[i32; 64]; 6],
     pub phase: [i32; 6],
+    /// Bonus for having the move.
+    pub tempo: i32,
+    /// Bonus for having two or more bishops.
+    pub bishop_pair: i32,
 }
 
 impl Eval {
@@ -220,6 +263,8 @@ impl Eval {
             phase: [
                 0, 1, 1, 2, 4, 0
             ],
+            tempo: 10,
+            bishop_pair: 50,
         }
     }
 
@@ -255,7 +300,9 @@ impl Eval {
                 // King
                 weights[715..779].try_into().unwrap()
             ],
-            phase: [0, 1, 1, 2, 4, 0]
+            phase: [0, 1, 1, 2, 4, 0],
+            tempo: self.tempo,
+            bishop_pair: self.bishop_pair,
         };
     }
 
@@ -270,6 +317,130 @@ impl Eval {
         score
     }
 
+    /// Score a position from the side to move's point of view, adding non-incremental terms to `state`.
+    pub fn evaluate(&self, board: &Board, state: &EvalState) -> i32 {
+        let terms = Self::mating(board)
+            + Self::space(board)
+            + Self::connected_pawns(board)
+            + self.bishop_pair(board)
+            + Self::tropism(board, state);
+        let score = state.get(board.side()) + if board.side() == Colour::White { terms } else { -terms };
+        let score = if board.is_wrong_bishop_rook_pawn_draw() { score / DRAW_SCALE } else { score };
+        score + self.tempo
+    }
+
+    /// When one side has only a king left against a queen or rook, reward driving that king to the edge
+    /// and bringing the other king close, which the piece-square tables alone don't teach.
+    /// The score is from White's point of view.
+    pub fn mating(board: &Board) -> i32 {
+        for winner in [Colour::White, Colour::Black] {
+            if board.piece_count(!winner) != 1 {
+                continue;
+            }
+
+            let mut has_major = false;
+            let mut winning_king = None;
+            let mut losing_king = None;
+            for piece in board.pieces() {
+                let square = board.square_of_piece(piece);
+                match (board.piece_from_bit(piece), piece.colour() == winner) {
+                    (Piece::Queen | Piece::Rook, true) => has_major = true,
+                    (Piece::King, true) => winning_king = Some(square),
+                    (Piece::King, false) => losing_king = Some(square),
+                    _ => {}
+                }
+            }
+
+            if let (true, Some(winning_king), Some(losing_king)) = (has_major, winning_king, losing_king) {
+                let edge = CENTRE_DISTANCE[losing_king.into_inner() as usize];
+                let proximity = 7 - i32::from(winning_king.chebyshev_distance(losing_king));
+                let bonus = MATING_EDGE * edge + MATING_PROXIMITY * proximity;
+                return if winner == Colour::White { bonus } else { -bonus };
+            }
+        }
+        0
+    }
+
+    /// Reward having both bishops, which together cover squares of both colours.
+    /// The score is from White's point of view.
+    pub fn bishop_pair(&self, board: &Board) -> i32 {
+        let pair = |colour| if board.piece_type_count(Piece::Bishop, colour) >= 2 { self.bishop_pair } else { 0 };
+        pair(Colour::White) - pair(Colour::Black)
+    }
+
+    /// Reward pieces for standing close to the enemy king, where they can join an attack,
+    /// fading out as material comes off and the kings are safer.
+    /// The score is from White's point of view.
+    pub fn tropism(board: &Board, state: &EvalState) -> i32 {
+        let mut kings = [None; 2];
+        for piece in board.pieces() {
+            if board.piece_from_bit(piece) == Piece::King {
+                kings[piece.colour() as usize] = Some(board.square_of_piece(piece));
+            }
+        }
+
+        let mut score = 0;
+        for piece in board.pieces() {
+            let colour = piece.colour();
+            if let Some(king) = kings[!colour as usize] {
+                let distance = i32::from(board.square_of_piece(piece).chebyshev_distance(king));
+                let bonus = TROPISM[board.piece_from_bit(piece) as usize] * (7 - distance);
+                score += if colour == Colour::White { bonus } else { -bonus };
+            }
+        }
+        score * state.phase() / 24
+    }
+
+    /// Reward controlling squares in the opponent's half with pawns where enemy pawns can't contest them,
+    /// which matters more the more minor pieces there are to use the space.
+    /// The score is from White's point of view.
+    pub fn space(board: &Board) -> i32 {
+        let pawn_attacks = [board.pawn_attacks(Colour::White), board.pawn_attacks(Colour::Black)];
+        let mut minors = [0; 2];
+        for piece in board.pieces() {
+            if matches!(board.piece_from_bit(piece), Piece::Knight | Piece::Bishop) {
+                minors[piece.colour() as usize] += 1;
+            }
+        }
+
+        let space = |colour: Colour| {
+            // The opponent's half is the top half of the board from `colour`'s point of view.
+            let opponent_half = if colour == Colour::White { 0xFFFF_FFFF_0000_0000 } else { 0x0000_0000_FFFF_FFFF };
+            let safe = pawn_attacks[colour as usize] & !pawn_attacks[!colour as usize] & opponent_half;
+            SPACE * safe.count_ones() as i32 * minors[colour as usize]
+        };
+        space(Colour::White) - space(Colour::Black)
+    }
+
+    /// Reward pawns that are defended by a friendly pawn or stand beside one, more so the further advanced.
+    /// The score is from White's point of view.
+    pub fn connected_pawns(board: &Board) -> i32 {
+        let pawn_attacks = [board.pawn_attacks(Colour::White), board.pawn_attacks(Colour::Black)];
+
+        let mut score = 0;
+        for piece in board.pieces() {
+            if board.piece_from_bit(piece) != Piece::Pawn {
+                continue;
+            }
+
+            let colour = piece.colour();
+            let square = board.square_of_piece(piece);
+            let friendly = |square: Option<Square>| {
+                square.is_some_and(|square| {
+                    board.piece_from_square(square) == Some(Piece::Pawn) && board.colour_occupied(colour) & (1 << square.into_inner()) != 0
+                })
+            };
+            let defended = pawn_attacks[colour as usize] & (1 << square.into_inner()) != 0;
+            let phalanx = friendly(square.east()) || friendly(square.west());
+
+            if defended || phalanx {
+                let bonus = CONNECTED[usize::from(square.relative_to(colour).into_inner() / 8)];
+                score += if colour == Colour::White { bonus } else { -bonus };
+            }
+        }
+        score
+    }
+
     pub fn update_eval(&self, board: &Board, m: &Move, old_score: &EvalState) -> EvalState {
         let from_piece = board.piece_from_square(m.from).unwrap();
         let mut old_score = old_score.clone();
@@ -283,7 +454,7 @@ impl Eval {
                 old_score.move_piece(self, from_piece, m.from, m.dest, board.side());
             },
             MoveType::Castle => {
-                if m.dest > m.from {
+                if m.castle_side() == Some(CastlingSide::King) {
                     let rook_from = m.dest.east().unwrap();
                     let rook_dest = m.dest.west().unwrap();
                     old_score.move_piece(self, Piece::Rook, rook_from, rook_dest, board.side());
@@ -313,3 +484,228 @@ impl Eval {
         old_score
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Eval, CP_SCALE};
+    use dorpsgek_movegen::Board;
+
+    #[test]
+    fn space_rewards_advanced_pawn_centre() {
+        // White has pushed to e5 and d5 where no Black pawn can challenge the squares they cover.
+        let big = Board::from_fen("r1bqkb1r/pp3ppp/2n1pn2/3PP3/8/2N2N2/PP3PPP/R1BQKB1R w KQkq - 0 1").unwrap();
+        let cramped = Board::from_fen("r1bqkb1r/pp3ppp/2n1pn2/8/8/2NPPN2/PP3PPP/R1BQKB1R w KQkq - 0 1").unwrap();
+        assert!(Eval::space(&big) > Eval::space(&cramped));
+        assert!(Eval::space(&big) > 0);
+
+        // Without minor pieces there is nothing to use the space.
+        let bare = Board::from_fen("4k3/pp3ppp/4p3/3PP3/8/8/PP3PPP/4K3 w - - 0 1").unwrap();
+        assert_eq!(Eval::space(&bare), 0);
+    }
+
+    #[test]
+    fn connected_pawns_beat_isolated_ones() {
+        let eval = Eval::new();
+        let score = |fen| {
+            let board = Board::from_fen(fen).unwrap();
+            (Eval::connected_pawns(&board), eval.evaluate(&board, &eval.eval(&board)))
+        };
+
+        // The same two pawns, isolated on c4 and e4, then as a chain and as a phalanx.
+        let isolated = score("4k3/8/8/8/2P1P3/8/8/4K3 w - - 0 1");
+        let chain = score("4k3/8/8/8/4P3/3P4/8/4K3 w - - 0 1");
+        let phalanx = score("4k3/8/8/8/3PP3/8/8/4K3 w - - 0 1");
+        assert_eq!(isolated.0, 0);
+        assert!(chain.0 > 0);
+        assert!(phalanx.0 > chain.0);
+        assert!(phalanx.1 > isolated.1);
+
+        // Advanced connected pawns count for more, and Black's count against White.
+        assert!(score("4k3/8/3PP3/8/8/8/8/4K3 w - - 0 1").0 > phalanx.0);
+        assert_eq!(score("4k3/8/8/3pp3/8/8/8/4K3 w - - 0 1").0, -phalanx.0);
+    }
+
+    #[test]
+    fn bishop_pair_beats_knight_pair() {
+        let eval = Eval::new();
+        let score = |fen| {
+            let board = Board::from_fen(fen).unwrap();
+            eval.evaluate(&board, &eval.eval(&board))
+        };
+
+        // White's bishops face Black's knights, all on their starting squares.
+        let fen = "1n2k1n1/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1";
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(eval.bishop_pair(&board), eval.bishop_pair);
+        assert!(score(fen) > 0);
+
+        // Without the bonus, the same position is worth less to White.
+        let mut without = Eval::new();
+        without.bishop_pair = 0;
+        assert_eq!(score(fen) - without.evaluate(&board, &without.eval(&board)), eval.bishop_pair);
+
+        // A lone bishop earns nothing.
+        let board = Board::from_fen("1n2k3/pppppppp/8/8/8/8/PPPPPPPP/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(eval.bishop_pair(&board), 0);
+    }
+
+    #[test]
+    fn tropism_rewards_pieces_near_the_enemy_king() {
+        let eval = Eval::new();
+        let tropism = |fen| {
+            let board = Board::from_fen(fen).unwrap();
+            Eval::tropism(&board, &eval.eval(&board))
+        };
+
+        // The full starting position is symmetrical.
+        assert_eq!(tropism("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), 0);
+
+        // White's queen on h5 is closer to Black's king than on d1.
+        let home = tropism("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+        let out = tropism("rnbqkbnr/pppp1ppp/8/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR b KQkq - 1 2");
+        assert!(out > home);
+
+        // With only kings and pawns left the term fades out entirely.
+        assert_eq!(tropism("4k3/pppp4/8/8/8/8/8/4K3 w - - 0 1"), 0);
+    }
+
+    #[test]
+    fn tempo_favours_side_to_move() {
+        let board = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4").unwrap();
+        let null = board.make_null();
+        let score = |eval: &Eval, board| eval.evaluate(board, &eval.eval(board));
+
+        // Having the move is worth the tempo to whichever side has it, so the scores aren't negations.
+        let mut eval = Eval::new();
+        assert_ne!(eval.tempo, 0);
+        assert_eq!(score(&eval, &board) + score(&eval, &null), 2 * eval.tempo);
+
+        eval.tempo = 0;
+        assert_eq!(score(&eval, &board), -score(&eval, &null));
+    }
+
+    #[test]
+    fn evaluation_is_colour_symmetric() {
+        use dorpsgek_movegen::Move;
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+        use tinyvec::ArrayVec;
+
+        // Tempo favours whoever is to move, so leave it out; everything else must not care which colour is which.
+        let mut eval = Eval::new();
+        eval.tempo = 0;
+        let score = |board: &Board| eval.evaluate(board, &eval.eval(board));
+
+        let mut rng = StdRng::seed_from_u64(965);
+        for fen in &[
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let mut board = Board::from_fen(fen).unwrap();
+            for _ in 0..200 {
+                // Both scores are from the side to move's view, and mirroring swaps sides, so they must match.
+                let mirrored = board.mirror();
+                assert_eq!(score(&board), score(&mirrored), "asymmetric evaluation of {}", board.to_fen());
+
+                let mut moves = ArrayVec::from([Move::default(); 256]);
+                moves.set_len(0);
+                board.generate(&mut moves);
+                match moves.choose(&mut rng) {
+                    Some(&m) => board = board.make(m),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quiet_moves_change_eval_smoothly() {
+        use dorpsgek_movegen::{Colour, Move};
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+        use tinyvec::ArrayVec;
+
+        let mut eval = Eval::new();
+        eval.tempo = 0;
+        let white_score = |board: &Board| {
+            let score = eval.evaluate(board, &eval.eval(board));
+            if board.side() == Colour::White { score } else { -score }
+        };
+
+        let mut rng = StdRng::seed_from_u64(977);
+        for fen in &[
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            // Promoted queens take the material past the starting phase.
+            "1qq1k3/pppppppp/8/8/8/8/PPPPPPPP/QQQQK1QQ w - - 0 1",
+        ] {
+            let mut board = Board::from_fen(fen).unwrap();
+            for _ in 0..200 {
+                // However much material there is, the taper interpolates between the middlegame and endgame scores.
+                let state = eval.eval(&board);
+                assert!(
+                    (state.pst_mg.min(state.pst_eg)..=state.pst_mg.max(state.pst_eg)).contains(&state.get(Colour::White)),
+                    "taper extrapolates in {}",
+                    board.to_fen()
+                );
+
+                let mut moves = ArrayVec::from([Move::default(); 256]);
+                moves.set_len(0);
+                board.generate(&mut moves);
+
+                // Quiet moves keep the material, so only the square tables and positional terms may move the score;
+                // a knight stepping into the corner is about the worst of those.
+                let before = white_score(&board);
+                for &m in moves.iter().filter(|m| !m.is_capture() && m.prom.is_none()) {
+                    let delta = (white_score(&board.make(m)) - before).abs();
+                    assert!(delta < 3 * CP_SCALE, "{} moves the score by {} in {}", m, delta, board.to_fen());
+                }
+
+                match moves.choose(&mut rng) {
+                    Some(&m) => board = board.make(m),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_bishop_draw_is_scaled_down() {
+        let mut eval = Eval::new();
+        eval.tempo = 0;
+        let score = |fen| {
+            let board = Board::from_fen(fen).unwrap();
+            eval.evaluate(&board, &eval.eval(&board))
+        };
+
+        // The same material, with Black's king in the corner the dark-squared bishop can't cover, and far away.
+        let draw = score("k7/8/8/8/P7/8/8/2B1K3 w - - 0 1");
+        let win = score("8/8/8/8/P7/8/7k/2B1K3 w - - 0 1");
+        assert!(draw.abs() < CP_SCALE / 2, "{}", draw);
+        assert!(win > 2 * CP_SCALE, "{}", win);
+    }
+
+    #[test]
+    fn mating_drives_lone_king_to_edge() {
+        let eval = Eval::new();
+        let score = |fen| {
+            let board = Board::from_fen(fen).unwrap();
+            eval.evaluate(&board, &eval.eval(&board))
+        };
+
+        // The same material, with Black's king confined to the corner rather than free in the centre.
+        let centre = score("8/8/8/3k4/8/8/8/K6Q w - - 0 1");
+        let edge = score("k7/8/8/8/8/8/8/K6Q w - - 0 1");
+        assert!(edge > centre);
+
+        // Bringing the White king closer is better too.
+        let near = score("k7/8/2K5/8/8/8/8/7Q w - - 0 1");
+        assert!(near > edge);
+
+        // Black sees the same thing from the other side, apart from the tempo moving to Black.
+        assert_eq!(score("k7/8/8/8/8/8/8/K6Q b - - 0 1"), 2 * eval.tempo - edge);
+
+        // Nothing changes while the defender has material.
+        let board = Board::from_fen("kp6/8/8/8/8/8/8/K6Q w - - 0 1").unwrap();
+        assert_eq!(Eval::mating(&board), 0);
+    }
+}
@@ -1,8 +1,11 @@
 #![warn(clippy::imprecise_flops, clippy::suboptimal_flops)]
 
 mod eval;
+mod movepicker;
 mod search;
+mod tt;
 mod tune;
 
-pub use search::Search;
-pub use tune::Tune;
+pub use search::{BestMove, IterationStats, Search, SearchOptions};
+pub use tt::ReplacementPolicy;
+pub use tune::{parse_labelled, Tune, TuneMethod, TuneOptions};
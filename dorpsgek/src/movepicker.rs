@@ -0,0 +1,344 @@
+use dorpsgek_movegen::{Board, Move};
+use tinyvec::ArrayVec;
+
+/// Quiet move scores, indexed by source and destination square.
+pub type History = [[i32; 64]; 64];
+
+type MoveList = ArrayVec<[Move; 256]>;
+type ScoreList = ArrayVec<[i32; 256]>;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    WinningCaptures,
+    Killers,
+    Quiets,
+    LosingCaptures,
+    Underpromotions,
+    Done,
+}
+
+/// An empty move list.
+fn move_list() -> MoveList {
+    let mut moves = ArrayVec::from([Move::default(); 256]);
+    moves.set_len(0);
+    moves
+}
+
+/// An empty score list.
+fn score_list() -> ScoreList {
+    let mut scores = ArrayVec::from([0; 256]);
+    scores.set_len(0);
+    scores
+}
+
+/// Remove and return the highest-scoring move.
+fn pick_best(moves: &mut MoveList, scores: &mut ScoreList) -> Option<Move> {
+    let (index, _) = scores.iter().enumerate().max_by_key(|(_, &score)| score)?;
+    scores.swap_remove(index);
+    Some(moves.swap_remove(index))
+}
+
+/// Yields the legal moves of a position in stages, best first, generating each stage only when needed:
+/// the hash move, captures that don't lose material, killers and the countermove, other quiet moves by history,
+/// then losing captures.
+/// Under-promotions can optionally be deferred until after everything else.
+pub struct MovePicker<'a> {
+    board: &'a Board,
+    tt_move: Option<Move>,
+    /// The killers, then the countermove.
+    killers: [Option<Move>; 3],
+    /// The hash move and killers already yielded, which generated lists must leave out.
+    tried: ArrayVec<[Move; 4]>,
+    stage: Stage,
+    captures: Option<(MoveList, ScoreList)>,
+    losing: MoveList,
+    quiets: Option<MoveList>,
+    quiet_scores: ScoreList,
+    defer_underpromotions: bool,
+    underpromotions: MoveList,
+}
+
+impl<'a> MovePicker<'a> {
+    pub fn new(board: &'a Board, tt_move: Option<Move>, killers: [Option<Move>; 2]) -> Self {
+        Self {
+            board,
+            tt_move,
+            killers: [killers[0], killers[1], None],
+            tried: ArrayVec::new(),
+            stage: Stage::TtMove,
+            captures: None,
+            losing: move_list(),
+            quiets: None,
+            quiet_scores: score_list(),
+            defer_underpromotions: false,
+            underpromotions: move_list(),
+        }
+    }
+
+    /// Try the quiet move that last refuted the opponent's move alongside the killers.
+    pub const fn countermove(mut self, countermove: Option<Move>) -> Self {
+        self.killers[2] = countermove;
+        self
+    }
+
+    /// Yield under-promotions last, since they are rarely better than promoting to a queen.
+    pub const fn defer_underpromotions(mut self) -> Self {
+        self.defer_underpromotions = true;
+        self
+    }
+
+    /// Move under-promotions out of `moves` if they are being deferred.
+    fn split_underpromotions(&mut self, moves: &mut MoveList) {
+        if self.defer_underpromotions {
+            for &m in moves.iter().filter(|m| m.is_underpromotion()) {
+                self.underpromotions.push(m);
+            }
+            moves.retain(|m| !m.is_underpromotion());
+        }
+    }
+
+    /// Split captures into winning and losing by static exchange evaluation.
+    fn store_captures(&mut self, mut moves: MoveList) {
+        moves.retain(|m| !self.tried.contains(m));
+        self.split_underpromotions(&mut moves);
+
+        let mut winning = move_list();
+        let mut scores = score_list();
+        for m in moves {
+            let see = self.board.see(m);
+            if see >= 0 {
+                winning.push(m);
+                scores.push(see);
+            } else {
+                self.losing.push(m);
+            }
+        }
+        self.captures = Some((winning, scores));
+    }
+
+    fn store_quiets(&mut self, mut moves: MoveList) {
+        moves.retain(|m| !self.tried.contains(m));
+        self.split_underpromotions(&mut moves);
+        self.quiets = Some(moves);
+    }
+
+    /// Capture generation doesn't know about check, so in check every evasion is generated at once
+    /// and sorted into both lists.
+    fn evasions(&mut self) {
+        let mut captures = move_list();
+        self.board.generate(&mut captures);
+        let mut quiets = move_list();
+        for &m in captures.iter().filter(|m| !m.is_capture()) {
+            quiets.push(m);
+        }
+        captures.retain(|m| m.is_capture());
+        self.store_captures(captures);
+        self.store_quiets(quiets);
+    }
+
+    /// Generate captures, split into winning and losing by static exchange evaluation.
+    fn captures(&mut self) -> &mut (MoveList, ScoreList) {
+        if self.captures.is_none() {
+            if self.board.in_check() {
+                self.evasions();
+            } else {
+                let mut moves = move_list();
+                self.board.generate_captures(&mut moves);
+                self.store_captures(moves);
+            }
+        }
+        self.captures.as_mut().unwrap()
+    }
+
+    fn quiets(&mut self) -> &mut MoveList {
+        if self.quiets.is_none() {
+            if self.board.in_check() {
+                self.evasions();
+            } else {
+                let mut moves = move_list();
+                self.board.generate(&mut moves);
+                moves.retain(|m| !m.is_capture());
+                self.store_quiets(moves);
+            }
+        }
+        self.quiets.as_mut().unwrap()
+    }
+
+    /// Check a move from outside the generator, the hash move or a killer, for legality without generating
+    /// any stage, and remember it so it isn't yielded again. Returns whether it was legal.
+    fn take(&mut self, m: Move) -> bool {
+        if self.tried.contains(&m) || !self.board.is_legal(m) {
+            return false;
+        }
+        self.tried.push(m);
+
+        // Lists generated before the move was taken still hold it.
+        if let Some((winning, scores)) = &mut self.captures {
+            if let Some(index) = winning.iter().position(|&c| c == m) {
+                winning.swap_remove(index);
+                scores.swap_remove(index);
+            }
+        }
+        if let Some(quiets) = &mut self.quiets {
+            quiets.retain(|&q| q != m);
+        }
+        self.losing.retain(|&c| c != m);
+        self.underpromotions.retain(|&u| u != m);
+        true
+    }
+
+    /// Return the next move, or `None` when every legal move has been returned.
+    /// History is passed in rather than held, so the search can keep updating it while the picker is alive.
+    pub fn next(&mut self, history: &History) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::WinningCaptures;
+                    if let Some(m) = self.tt_move {
+                        // A hash collision might give a move that isn't legal here.
+                        if self.take(m) {
+                            return Some(m);
+                        }
+                        self.tt_move = None;
+                    }
+                }
+                Stage::WinningCaptures => {
+                    let (winning, scores) = self.captures();
+                    if let Some(m) = pick_best(winning, scores) {
+                        return Some(m);
+                    }
+                    self.stage = Stage::Killers;
+                }
+                Stage::Killers => {
+                    while let Some(killer) = self.killers.iter_mut().find_map(Option::take) {
+                        if !killer.is_capture() && self.take(killer) {
+                            return Some(killer);
+                        }
+                    }
+
+                    // Score the remaining quiets for the next stage.
+                    self.quiets();
+                    for m in self.quiets.iter().flat_map(|q| q.iter()) {
+                        self.quiet_scores.push(history[usize::from(m.from.into_inner())][usize::from(m.dest.into_inner())]);
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    let quiets = self.quiets.as_mut().unwrap();
+                    if let Some(m) = pick_best(quiets, &mut self.quiet_scores) {
+                        return Some(m);
+                    }
+                    self.stage = Stage::LosingCaptures;
+                }
+                Stage::LosingCaptures => {
+                    if !self.losing.is_empty() {
+                        return Some(self.losing.remove(0));
+                    }
+                    self.stage = Stage::Underpromotions;
+                }
+                Stage::Underpromotions => {
+                    // Both lists must exist for every under-promotion to have been split off.
+                    self.captures();
+                    self.quiets();
+                    if !self.underpromotions.is_empty() {
+                        return Some(self.underpromotions.remove(0));
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{move_list, MovePicker};
+    use dorpsgek_movegen::{Board, Move};
+
+    fn drain(board: &Board, tt_move: Option<Move>, killers: [Option<Move>; 2]) -> Vec<String> {
+        let history = [[0; 64]; 64];
+        let mut picker = MovePicker::new(board, tt_move, killers);
+        let mut moves = Vec::new();
+        while let Some(m) = picker.next(&history) {
+            moves.push(m.to_string());
+        }
+        moves
+    }
+
+    #[test]
+    fn picker_yields_every_legal_move_once() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/8/8/8/8/8/8/r3K3 w - - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mut moves = move_list();
+            board.generate(&mut moves);
+            let mut expected = moves.iter().map(ToString::to_string).collect::<Vec<_>>();
+            expected.sort();
+
+            let tt_move = moves.iter().rev().copied().next();
+            let killers = [moves.iter().find(|m| !m.is_capture()).copied(), tt_move];
+            for (tt_move, killers) in [(None, [None, None]), (tt_move, killers)] {
+                let mut picked = drain(&board, tt_move, killers);
+                if let Some(tt_move) = tt_move {
+                    assert_eq!(picked[0], tt_move.to_string());
+                }
+                picked.sort();
+                assert_eq!(picked, expected, "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn picker_rejects_illegal_hash_move() {
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let other = Board::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        let mut moves = move_list();
+        other.generate(&mut moves);
+
+        let picked = drain(&board, Some(moves[0]), [Some(moves[1]), None]);
+        assert_eq!(picked.len(), 20);
+    }
+
+    #[test]
+    fn picker_yields_quiet_hash_move_without_generating() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut moves = move_list();
+        board.generate(&mut moves);
+        let quiet = moves.iter().copied().find(|m| !m.is_capture()).unwrap();
+
+        let history = [[0; 64]; 64];
+        let mut picker = MovePicker::new(&board, Some(quiet), [None, None]);
+        assert!(picker.next(&history) == Some(quiet));
+        assert!(picker.captures.is_none() && picker.quiets.is_none());
+    }
+
+    #[test]
+    fn picker_defers_underpromotions() {
+        let board = Board::from_fen("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let history = [[0; 64]; 64];
+
+        let mut picker = MovePicker::new(&board, None, [None, None]);
+        let quiets = picker.quiets().len();
+        let mut deferred = MovePicker::new(&board, None, [None, None]).defer_underpromotions();
+        assert_eq!(deferred.quiets().len(), quiets - 3);
+
+        // Every move still comes out, with the under-promotions last.
+        let mut picker = MovePicker::new(&board, None, [None, None]).defer_underpromotions();
+        let mut moves = Vec::new();
+        while let Some(m) = picker.next(&history) {
+            moves.push(m);
+        }
+        let first_under = moves.iter().position(Move::is_underpromotion).unwrap();
+        assert_eq!(moves.len() - first_under, 6);
+        assert!(moves[first_under..].iter().all(Move::is_underpromotion));
+        assert_eq!(moves.len(), drain(&board, None, [None, None]).len());
+    }
+}
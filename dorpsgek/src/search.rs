@@ -1,14 +1,217 @@
-use dorpsgek_movegen::{Board, Move};
+use std::{
+    cell::Cell,
+    convert::TryFrom,
+    sync::{
+        atomic::{AtomicBool, AtomicU16, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use dorpsgek_movegen::{Board, Move, Piece};
 use tinyvec::ArrayVec;
 
-use crate::eval::{Eval, EvalState};
+use crate::eval::{Eval, EvalState, MAX_EVAL};
+use crate::movepicker::{History, MovePicker};
+use crate::tt::{Bound, Entry, ReplacementPolicy, TranspositionTable, NO_MOVE};
+
+/// The score for delivering mate now; mate in `n` plies scores `MATE_VALUE - n`.
+const MATE_VALUE: i32 = 30_000;
+
+/// The deepest iteration `analyze` will search, limited by the length of a PV.
+const MAX_DEPTH: i32 = 32;
+
+/// No node is searched further than this many plies from the root, however the line was extended, so the
+/// recursion can't overflow the stack; nodes at the limit return their static evaluation.
+const MAX_PLY: i32 = 128;
+
+/// Scores beyond this are mate scores, and need adjusting by ply for storage in the hash table.
+const MATE_BOUND: i32 = MATE_VALUE - 1_000;
+
+// A static evaluation must never be mistaken for a mate score.
+const _: () = assert!(MAX_EVAL < MATE_BOUND);
+
+/// The shallowest depth at which the hash move is tested for being singular.
+const SINGULAR_DEPTH: i32 = 4;
+
+/// How far below the hash score, per ply of depth, every other move must fail for the hash move to be singular.
+const SINGULAR_MARGIN: i32 = 2;
+
+/// Quiescence only looks for stalemate when the side to move has at most this many pieces, including its king,
+/// since generating every move in every node would be too slow.
+const STALEMATE_PIECES: u32 = 3;
+
+/// Internal iterative deepening only applies at this depth and above.
+const IID_DEPTH: i32 = 4;
+
+/// How much shallower than the node itself the internal iterative deepening search is.
+const IID_REDUCTION: i32 = 2;
 
-const MATE_VALUE: i32 = 10_000;
+/// Late-move pruning only applies at this depth and below.
+const LMP_DEPTH: i32 = 3;
+
+/// At the root, a move that doesn't repeat the game is preferred over a repetition draw scoring at most this much
+/// better, so a winning side doesn't settle for a draw to save a few centipawns.
+const REPETITION_MARGIN: i32 = 25;
+
+/// How many quiet moves are searched at `depth` before late-move pruning starts.
+const fn late_move_count(depth: i32) -> usize {
+    3 + (depth * depth) as usize
+}
+
+/// Convert a score relative to the root into one relative to this node.
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score > MATE_BOUND {
+        score + ply
+    } else if score < -MATE_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+/// Convert a score relative to a node into one relative to the root.
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score > MATE_BOUND {
+        score - ply
+    } else if score < -MATE_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+/// A key that sorts moves in the same order as their coordinate notation, without formatting them.
+fn coordinate_key(m: Move) -> (u8, u8, u8, u8, Option<char>) {
+    let (from, dest) = (m.from.into_inner(), m.dest.into_inner());
+    let prom = m.prom.map(|piece| match piece {
+        Piece::Knight => 'n',
+        Piece::Bishop => 'b',
+        Piece::Rook => 'r',
+        Piece::Queen => 'q',
+        Piece::Pawn => 'p',
+        Piece::King => 'k',
+    });
+    (from % 8, from / 8, dest % 8, dest / 8, prom)
+}
+
+/// Search configuration.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchOptions {
+    /// The number of transposition table entries.
+    pub tt_size: usize,
+    /// Which transposition table entries to overwrite when the table is full.
+    pub replacement: ReplacementPolicy,
+    /// Whether quiescence search also tries quiet checks at its first ply.
+    pub quiescence_checks: bool,
+    /// Whether to extend a hash move when every alternative is clearly worse.
+    pub singular_extensions: bool,
+    /// Whether to extend recaptures that don't lose material, to finish exchanges before the horizon.
+    pub recapture_extensions: bool,
+    /// Whether to try under-promotions after every other move.
+    pub defer_underpromotions: bool,
+    /// Whether to try the quiet move that last refuted the previous move early.
+    pub countermoves: bool,
+    /// Whether to skip late quiet moves with a poor history near the leaves.
+    pub late_move_pruning: bool,
+    /// Whether PV nodes without a hash move first run a shallower search to find one.
+    pub internal_iterative_deepening: bool,
+    /// How many iterations in a row `search_timed` must keep the same best move before stopping early.
+    pub stable_iterations: u32,
+    /// How far, in centipawns, the score may move between iterations and still count as stable.
+    pub stable_margin: i32,
+    /// Milliseconds `search_timed` holds back from its budget for communication lag, so the move arrives in time.
+    pub move_overhead_ms: u64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            tt_size: 1 << 20,
+            replacement: ReplacementPolicy::default(),
+            quiescence_checks: false,
+            singular_extensions: false,
+            recapture_extensions: false,
+            defer_underpromotions: false,
+            countermoves: true,
+            late_move_pruning: false,
+            internal_iterative_deepening: false,
+            stable_iterations: 6,
+            stable_margin: 15,
+            move_overhead_ms: 10,
+        }
+    }
+}
+
+/// The best root move found so far, which another thread can read while a search runs.
+pub struct BestMove(AtomicU16);
+
+impl BestMove {
+    fn new() -> Self {
+        Self(AtomicU16::new(NO_MOVE))
+    }
+
+    /// The best move found so far, or `None` before any root move has been searched.
+    pub fn get(&self) -> Option<Move> {
+        Move::unpack(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, m: Option<Move>) {
+        self.0.store(m.map_or(NO_MOVE, Move::pack), Ordering::Relaxed);
+    }
+}
+
+/// What one iteration of iterative deepening cost, passed to the `info` callback after it completes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IterationStats {
+    /// Nodes searched in this iteration, including quiescence nodes.
+    pub nodes: u64,
+    /// Time taken by this iteration.
+    pub elapsed: Duration,
+    /// The part of `elapsed` spent in quiescence search, estimated from the share of quiescence nodes.
+    pub quiesce_time: Duration,
+    /// This iteration's nodes divided by the previous iteration's, or `None` for the first iteration.
+    /// Good move ordering keeps the effective branching factor low.
+    pub branching_factor: Option<f64>,
+}
 
 pub struct Search {
     eval: Eval,
+    tt: TranspositionTable,
+    quiescence_checks: bool,
+    singular_extensions: bool,
+    recapture_extensions: bool,
+    defer_underpromotions: bool,
+    use_countermoves: bool,
+    late_move_pruning: bool,
+    internal_iterative_deepening: bool,
+    stable_iterations: u32,
+    stable_margin: i32,
+    move_overhead: Duration,
+    stop: Arc<AtomicBool>,
+    /// The best root move so far, updated as soon as a root move raises alpha.
+    best_move: Arc<BestMove>,
+    /// When a timed search must stop.
+    deadline: Option<Instant>,
+    /// Whether the deadline has passed; kept apart from `stop` so the caller's flag is left alone.
+    timed_out: Cell<bool>,
+    /// Quiet moves that caused a beta cutoff, by ply.
+    killers: [[Option<Move>; 2]; MAX_DEPTH as usize],
+    history: History,
+    /// Quiet moves that refuted a move, by the moving piece and its destination.
+    countermoves: [[Option<Move>; 64]; 6],
+    /// The principal variation of the last completed iteration, and the hash of the position it starts from.
+    last_pv: ArrayVec<[Move; 32]>,
+    last_pv_key: u64,
+    /// Whether the current node lies on `last_pv`.
+    follow_pv: bool,
+    /// The repetition keys of the game's positions up to and including the root, oldest first.
+    game_history: Vec<u64>,
     nodes: u64,
     qnodes: u64,
+    /// Time spent in `search_root`, and the part of it spent in quiescence search.
+    search_time: Duration,
+    quiesce_time: Duration,
 }
 
 impl Default for Search {
@@ -19,20 +222,65 @@ impl Default for Search {
 
 impl Search {
     pub fn new() -> Self {
+        Self::with_options(SearchOptions::default())
+    }
+
+    pub fn with_options(options: SearchOptions) -> Self {
         Self {
             eval: Eval::new(),
+            tt: TranspositionTable::new(options.tt_size, options.replacement),
+            quiescence_checks: options.quiescence_checks,
+            singular_extensions: options.singular_extensions,
+            recapture_extensions: options.recapture_extensions,
+            defer_underpromotions: options.defer_underpromotions,
+            use_countermoves: options.countermoves,
+            late_move_pruning: options.late_move_pruning,
+            internal_iterative_deepening: options.internal_iterative_deepening,
+            stable_iterations: options.stable_iterations,
+            stable_margin: options.stable_margin,
+            move_overhead: Duration::from_millis(options.move_overhead_ms),
+            stop: Arc::new(AtomicBool::new(false)),
+            best_move: Arc::new(BestMove::new()),
+            deadline: None,
+            timed_out: Cell::new(false),
+            killers: [[None; 2]; MAX_DEPTH as usize],
+            history: [[0; 64]; 64],
+            countermoves: [[None; 64]; 6],
+            last_pv: ArrayVec::new(),
+            last_pv_key: 0,
+            follow_pv: false,
+            game_history: Vec::new(),
             nodes: 0,
             qnodes: 0,
+            search_time: Duration::ZERO,
+            quiesce_time: Duration::ZERO,
         }
     }
 
-    fn quiesce(&mut self, board: &Board, mut alpha: i32, beta: i32, eval: &EvalState) -> i32 {
-        let eval_int = eval.get(board.side());
+    /// Like `search`, this is fail-soft.
+    fn quiesce(&mut self, board: &Board, mut alpha: i32, beta: i32, eval: &EvalState, qply: i32, mate: i32) -> i32 {
+        if MATE_VALUE - mate >= MAX_PLY {
+            return self.eval.evaluate(board, eval);
+        }
+
+        // There is no standing pat in check, so every evasion must be searched.
+        if board.in_check() {
+            return self.quiesce_evasions(board, alpha, beta, eval, qply, mate);
+        }
+
+        // Standing pat would score a stalemate by material, and lead a winning side into it.
+        if board.piece_count(board.side()) <= STALEMATE_PIECES && board.is_stalemate() {
+            return 0;
+        }
+
+        let eval_int = self.eval.evaluate(board, eval);
+        debug_assert!(eval_int.abs() < MATE_BOUND, "evaluation {} is in the mate band", eval_int);
 
-        if eval_int >= beta {
-            return beta;
+        let mut best = eval_int;
+        if best >= beta {
+            return best;
         }
-        alpha = alpha.max(eval_int);
+        alpha = alpha.max(best);
 
         board.generate_captures_incremental(|m| {
             self.qnodes += 1;
@@ -46,22 +294,111 @@ impl Search {
             }
 
             let board = board.make(m);
-            alpha = alpha.max(-self.quiesce(&board, -beta, -alpha, &eval));
+            let score = -self.quiesce(&board, -beta, -alpha, &eval, qply + 1, mate - 1);
+            best = best.max(score);
+            alpha = alpha.max(score);
 
-            if alpha >= beta {
-                alpha = beta;
-                return false;
-            }
-            true
+            alpha < beta
         });
 
-        alpha
+        // Quiet checks are limited to the first ply and to moves that don't lose material, to keep the tree small.
+        if self.quiescence_checks && qply == 0 && best < beta {
+            let moves: [Move; 256] = [Move::default(); 256];
+            let mut moves = ArrayVec::from(moves);
+            moves.set_len(0);
+            board.generate_checks(&mut moves);
+
+            for m in moves {
+                if !board.see_ge(m, 0) {
+                    continue;
+                }
+
+                self.qnodes += 1;
+
+                let eval = self.eval.update_eval(board, &m, eval);
+                let board = board.make(m);
+                let score = -self.quiesce(&board, -beta, -alpha, &eval, qply + 1, mate - 1);
+                best = best.max(score);
+                alpha = alpha.max(score);
+
+                if best >= beta {
+                    return best;
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Search every move out of check, since standing pat would miss being mated.
+    fn quiesce_evasions(&mut self, board: &Board, mut alpha: i32, beta: i32, eval: &EvalState, qply: i32, mate: i32) -> i32 {
+        let moves: [Move; 256] = [Move::default(); 256];
+        let mut moves = ArrayVec::from(moves);
+        moves.set_len(0);
+        board.generate(&mut moves);
+
+        if moves.is_empty() {
+            return -mate;
+        }
+
+        let mut best = -MATE_VALUE;
+        for m in moves {
+            self.qnodes += 1;
+
+            let eval = self.eval.update_eval(board, &m, eval);
+            let board = board.make(m);
+            let score = -self.quiesce(&board, -beta, -alpha, &eval, qply + 1, mate - 1);
+            best = best.max(score);
+            alpha = alpha.max(score);
+
+            if best >= beta {
+                return best;
+            }
+        }
+
+        best
     }
 
-    fn search(&mut self, board: &Board, depth: i32, mut alpha: i32, beta: i32, eval: &EvalState, pv: &mut ArrayVec<[Move; 32]>, mate: i32) -> i32 {
+    /// The search is fail-soft: a score at or above `beta` is a lower bound and one at or below `alpha` an upper bound,
+    /// either of which may lie beyond the window.
+    /// `last_move` is the move that led here, if any; a null move doesn't count.
+    fn search(&mut self, board: &Board, depth: i32, mut alpha: i32, beta: i32, eval: &EvalState, pv: &mut ArrayVec<[Move; 32]>, mate: i32, last_move: Option<Move>) -> i32 {
+        let ply = MATE_VALUE - mate;
+        if ply >= MAX_PLY {
+            pv.set_len(0);
+            return self.eval.evaluate(board, eval);
+        }
+
         if depth <= 0 {
             pv.set_len(0);
-            return self.quiesce(board, alpha, beta, eval);
+            return self.quiesce(board, alpha, beta, eval, 0, mate);
+        }
+
+        // A stopped search returns a meaningless score, which the caller discards.
+        if self.stopped() {
+            return 0;
+        }
+
+        // On the previous iteration's PV, its move here is tried first.
+        let pv_move = if self.follow_pv { usize::try_from(ply).ok().and_then(|ply| self.last_pv.get(ply)).copied() } else { None };
+        self.follow_pv = pv_move.is_some();
+
+        let key = board.hash();
+        let tt_entry = self.tt.probe(key);
+
+        // Hash cutoffs are only taken in zero-window nodes, to keep the PV intact.
+        if let Some(entry) = tt_entry {
+            if entry.depth >= depth && beta - alpha == 1 {
+                let score = score_from_tt(entry.score, ply);
+                let cutoff = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if cutoff {
+                    return score;
+                }
+            }
         }
 
         const R: i32 = 3;
@@ -69,40 +406,159 @@ impl Search {
         if !board.in_check() && depth >= R {
             let board = board.make_null();
             let mut child_pv = ArrayVec::new();
-            let score = -self.search(&board, depth - 1 - R, -beta, -beta + 1, eval, &mut child_pv, mate);
+            let follow_pv = std::mem::replace(&mut self.follow_pv, false);
+            let score = -self.search(&board, depth - 1 - R, -beta, -beta + 1, eval, &mut child_pv, mate, None);
+            self.follow_pv = follow_pv;
 
+            if self.stopped() {
+                return 0;
+            }
+            // Passing isn't legal, so a mate found after one isn't proven.
             if score >= beta {
-                return beta;
+                return if score >= MATE_BOUND { beta } else { score };
             }
         }
 
-        let moves: [Move; 256] = [Move::default(); 256];
-        let mut moves = ArrayVec::from(moves);
-        moves.set_len(0);
-        board.generate(&mut moves);
+        let mut tt_move = tt_entry.and_then(|entry| entry.m);
 
-        // Is this checkmate or stalemate?
-        if moves.is_empty() {
-            if board.in_check() {
-                return -mate;
-            } else {
+        // A PV node with nothing to try first is searched shallower to find a move, which the hash table then holds.
+        if self.internal_iterative_deepening && depth >= IID_DEPTH && beta - alpha > 1 && pv_move.is_none() && tt_move.is_none() {
+            let mut child_pv = ArrayVec::new();
+            self.search(board, depth - IID_REDUCTION, alpha, beta, eval, &mut child_pv, mate, last_move);
+            if self.stopped() {
                 return 0;
             }
+            tt_move = self.tt.probe(key).and_then(|entry| entry.m);
         }
 
-        for m in moves {
+        // Extending must not overflow the PV, so stay within the maximum depth.
+        let singular = match (tt_entry, tt_move) {
+            (Some(entry), Some(tt_move))
+                if self.singular_extensions
+                    && depth >= SINGULAR_DEPTH
+                    && ply + depth < MAX_DEPTH
+                    && entry.depth >= depth - 3
+                    && entry.bound != Bound::Upper
+                    && entry.score.abs() < MATE_BOUND =>
+            {
+                let follow_pv = std::mem::replace(&mut self.follow_pv, false);
+                let singular = self.is_singular(board, tt_move, score_from_tt(entry.score, ply), depth, eval, mate);
+                self.follow_pv = follow_pv;
+                singular
+            }
+            _ => false,
+        };
+        if self.stopped() {
+            return 0;
+        }
+
+        let killers = usize::try_from(ply).ok().and_then(|ply| self.killers.get(ply)).copied().unwrap_or_default();
+        // The countermove table is indexed by the piece that moved last and where it went.
+        let counter_slot = last_move.filter(|_| self.use_countermoves).and_then(|last| Some((board.piece_from_square(last.dest)? as usize, usize::from(last.dest.into_inner()))));
+        let countermove = counter_slot.and_then(|(piece, dest)| self.countermoves[piece][dest]);
+        let mut picker = MovePicker::new(board, pv_move.or(tt_move), killers).countermove(countermove);
+        if self.defer_underpromotions {
+            picker = picker.defer_underpromotions();
+        }
+
+        let old_alpha = alpha;
+        let mut best_score = -MATE_VALUE;
+        let mut best_move = None;
+        let mut best_repeats = false;
+        let mut searched = 0;
+        // Quiet moves searched so far, which are penalised if a later quiet move causes a cutoff.
+        let mut quiets = ArrayVec::from([Move::default(); 256]);
+        quiets.set_len(0);
+        let prune_late_moves = self.late_move_pruning && ply > 0 && depth <= LMP_DEPTH && !board.in_check();
+
+        while let Some(m) = picker.next(&self.history) {
+            let quiet = !m.is_capture();
+            // Near the leaves, once enough quiet moves have been tried, ones that have done badly elsewhere are
+            // unlikely to help here.
+            if prune_late_moves
+                && quiet
+                && m.prom.is_none()
+                && quiets.len() >= late_move_count(depth)
+                && self.history[usize::from(m.from.into_inner())][usize::from(m.dest.into_inner())] < 0
+                && !board.gives_check(m)
+            {
+                continue;
+            }
+
             self.nodes += 1;
+            searched += 1;
+            // Only the PV move's subtree continues along the PV.
+            if Some(m) != pv_move {
+                self.follow_pv = false;
+            }
 
             let mut child_pv = ArrayVec::new();
             let eval = self.eval.update_eval(board, &m, eval);
-            let board = board.make(m);
-            let score = -self.search(&board, depth - 1, -beta, -alpha, &eval, &mut child_pv, mate - 1);
+            let child = board.make(m);
+            let recapture = self.recapture_extensions
+                && ply + depth < MAX_DEPTH
+                && m.is_capture()
+                && last_move.is_some_and(|last| last.is_capture() && last.dest == m.dest)
+                && board.see_ge(m, 0);
+            let extension = i32::from((singular && Some(m) == tt_move) || recapture);
+            // At the root, a tie goes to the move with the smaller coordinate, so the best move doesn't depend on
+            // move order. Lowering alpha by one lets a tie return exactly alpha instead of failing low.
+            let tie_break = ply == 0 && best_move.is_some_and(|best: Move| coordinate_key(m) < coordinate_key(best));
+            // A root move back into an earlier position of the game is scored as a draw. When the best move so far
+            // is such a draw, the window is lowered so a progressing move close behind it can still be chosen.
+            let repeats = ply == 0 && child.can_repeat_within(&self.game_history);
+            let progresses = best_repeats && !repeats;
+            let floor = if progresses {
+                alpha - REPETITION_MARGIN
+            } else if tie_break {
+                alpha - 1
+            } else {
+                alpha
+            };
+            let score = if repeats {
+                0
+            } else {
+                -self.search(&child, depth - 1 + extension, -beta, -floor, &eval, &mut child_pv, mate - 1, Some(m))
+            };
 
+            if self.stopped() {
+                return 0;
+            }
+            if !repeats {
+                best_score = best_score.max(score);
+            }
             if score >= beta {
-                return beta;
+                if quiet {
+                    self.update_quiet_stats(m, ply, depth, counter_slot, &quiets);
+                }
+                self.tt.store(Entry {
+                    key,
+                    m: Some(m),
+                    score: score_to_tt(score, ply),
+                    depth,
+                    bound: Bound::Lower,
+                });
+                return score;
             }
-            if score > alpha {
+            if quiet {
+                quiets.push(m);
+            }
+            let better = if progresses {
+                score >= alpha - REPETITION_MARGIN
+            } else if repeats && best_move.is_some() && !best_repeats {
+                score > alpha + REPETITION_MARGIN
+            } else {
+                score > alpha || (tie_break && score == alpha)
+            };
+            if better {
                 alpha = score;
+                best_move = Some(m);
+                best_repeats = repeats;
+                if ply == 0 {
+                    // A progressing move may have been preferred to a better-scoring draw.
+                    best_score = score;
+                    self.best_move.set(Some(m));
+                }
                 pv.set_len(0);
                 pv.push(m);
                 for m in child_pv {
@@ -111,12 +567,250 @@ impl Search {
             }
         }
 
-        alpha
+        // Is this checkmate or stalemate?
+        if searched == 0 {
+            if board.in_check() {
+                return -mate;
+            } else {
+                return 0;
+            }
+        }
+
+        self.tt.store(Entry {
+            key,
+            m: best_move,
+            score: score_to_tt(best_score, ply),
+            depth,
+            bound: if alpha > old_alpha { Bound::Exact } else { Bound::Upper },
+        });
+
+        best_score
+    }
+
+    /// Returns true if every move except `tt_move` fails low, at reduced depth, against a bound below the hash score.
+    /// Then `tt_move` is the only move that holds the position, and is worth searching deeper.
+    fn is_singular(&mut self, board: &Board, tt_move: Move, tt_score: i32, depth: i32, eval: &EvalState, mate: i32) -> bool {
+        let singular_beta = tt_score - SINGULAR_MARGIN * depth;
+
+        let mut moves = ArrayVec::from([Move::default(); 256]);
+        moves.set_len(0);
+        board.generate(&mut moves);
+
+        // A hash collision might give a move that isn't legal here.
+        if !moves.contains(&tt_move) {
+            return false;
+        }
+
+        for m in moves {
+            if m == tt_move {
+                continue;
+            }
+
+            let mut child_pv = ArrayVec::new();
+            let eval = self.eval.update_eval(board, &m, eval);
+            let child = board.make(m);
+            let score = -self.search(&child, (depth - 1) / 2 - 1, -singular_beta, -singular_beta + 1, &eval, &mut child_pv, mate - 1, Some(m));
+
+            if self.stopped() || score >= singular_beta {
+                return false;
+            }
+        }
+        true
     }
 
+    /// Search `board` to `depth`, filling `pv` with the principal variation and returning its score.
+    /// Of root moves with equal scores, the one whose coordinate notation sorts first is chosen.
+    /// A root move repeating a position from the game history is a draw, and loses to any move that doesn't repeat
+    /// unless it scores more than `REPETITION_MARGIN` better.
     pub fn search_root(&mut self, board: &Board, depth: i32, pv: &mut ArrayVec<[Move; 32]>) -> i32 {
+        let (start, nodes, qnodes) = (Instant::now(), self.nodes, self.qnodes);
+        let eval = self.eval.eval(board);
+        // A PV from another position would only mislead ordering.
+        self.follow_pv = self.last_pv_key == board.hash();
+        if !self.follow_pv {
+            self.best_move.set(None);
+        }
+        self.game_history.push(board.repetition_key());
+        let score = self.search(board, depth, -100_000, 100_000, &eval, pv, MATE_VALUE, None);
+        self.game_history.pop();
+        if !self.stopped() {
+            self.last_pv = *pv;
+            self.last_pv_key = board.hash();
+        }
+        // Reading the clock around every quiescence search would slow it down, so its share of the time is
+        // estimated from its share of the nodes.
+        let elapsed = start.elapsed();
+        let (nodes, qnodes) = (self.nodes - nodes, self.qnodes - qnodes);
+        if nodes + qnodes > 0 {
+            #[allow(clippy::cast_precision_loss)]
+            let share = qnodes as f64 / (nodes + qnodes) as f64;
+            self.quiesce_time += elapsed.mul_f64(share);
+        }
+        self.search_time += elapsed;
+        score
+    }
+
+    /// Run one iteration of iterative deepening through `search_root`, measuring it against `previous`.
+    fn search_iteration(&mut self, board: &Board, depth: i32, pv: &mut ArrayVec<[Move; 32]>, previous: Option<&IterationStats>) -> (i32, IterationStats) {
+        let (nodes, search_time, quiesce_time) = (self.nodes + self.qnodes, self.search_time, self.quiesce_time);
+        let score = self.search_root(board, depth, pv);
+        let nodes = self.nodes + self.qnodes - nodes;
+        #[allow(clippy::cast_precision_loss)]
+        let stats = IterationStats {
+            nodes,
+            elapsed: self.search_time - search_time,
+            quiesce_time: self.quiesce_time - quiesce_time,
+            branching_factor: previous.filter(|previous| previous.nodes > 0).map(|previous| nodes as f64 / previous.nodes as f64),
+        };
+        (score, stats)
+    }
+
+    /// Look for a mate in at most `n` moves, returning the shortest mating line found.
+    /// The window only admits mate scores, so lines that don't mate fail low quickly.
+    pub fn search_mate(&mut self, board: &Board, n: u32) -> Option<Vec<Move>> {
         let eval = self.eval.eval(board);
-        self.search(board, depth, -100_000, 100_000, &eval, pv, MATE_VALUE)
+        let max_depth = i32::try_from(n).ok()?.saturating_mul(2).saturating_sub(1).min(MAX_DEPTH);
+
+        for depth in (1..=max_depth).step_by(2) {
+            // Mating in `depth` plies scores MATE_VALUE - depth.
+            let alpha = MATE_VALUE - depth - 1;
+            let mut pv = ArrayVec::new();
+            self.follow_pv = false;
+            let score = self.search(board, depth, alpha, MATE_VALUE, &eval, &mut pv, MATE_VALUE, None);
+            if score > alpha {
+                return Some(pv.to_vec());
+            }
+        }
+        None
+    }
+
+    /// Search with iterative deepening until `stop` is set, a mate is proven, or the maximum depth is reached.
+    /// After each completed iteration, `info` is called with the depth, score, principal variation and iteration
+    /// statistics.
+    /// Returns the principal variation of the last completed iteration.
+    pub fn analyze<F: FnMut(i32, i32, &[Move], &IterationStats)>(&mut self, board: &Board, stop: Arc<AtomicBool>, mut info: F) -> ArrayVec<[Move; 32]> {
+        self.stop = stop;
+
+        let mut best_pv = ArrayVec::new();
+        let mut last_stats = None;
+        for depth in 1..=MAX_DEPTH {
+            let mut pv = ArrayVec::new();
+            let (score, stats) = self.search_iteration(board, depth, &mut pv, last_stats.as_ref());
+
+            if self.stopped() {
+                break;
+            }
+
+            info(depth, score, &pv, &stats);
+            last_stats = Some(stats);
+            best_pv = pv;
+
+            if score.abs() > MATE_BOUND {
+                break;
+            }
+        }
+
+        // Let later searches run once the caller has stopped this one.
+        self.stop = Arc::new(AtomicBool::new(false));
+        best_pv
+    }
+
+    /// Search with iterative deepening for at most `budget` less the move overhead, or until `stop` is set, stopping early once a mate
+    /// is proven or the best move has held for `stable_iterations` iterations with the score staying within `stable_margin`.
+    /// After each completed iteration, `info` is called with the depth, score, principal variation and iteration
+    /// statistics.
+    /// Returns the principal variation of the last completed iteration.
+    pub fn search_timed<F: FnMut(i32, i32, &[Move], &IterationStats)>(&mut self, board: &Board, budget: Duration, stop: Arc<AtomicBool>, mut info: F) -> ArrayVec<[Move; 32]> {
+        self.stop = stop;
+        self.deadline = Some(Instant::now() + budget.saturating_sub(self.move_overhead));
+
+        let mut best_pv: ArrayVec<[Move; 32]> = ArrayVec::new();
+        let mut last_score = 0;
+        let mut stable = 0;
+        let mut last_stats = None;
+        for depth in 1..=MAX_DEPTH {
+            let mut pv = ArrayVec::new();
+            let (score, stats) = self.search_iteration(board, depth, &mut pv, last_stats.as_ref());
+
+            if self.stopped() {
+                break;
+            }
+
+            info(depth, score, &pv, &stats);
+            last_stats = Some(stats);
+            if best_pv.first() == pv.first() && (score - last_score).abs() <= self.stable_margin {
+                stable += 1;
+            } else {
+                stable = 0;
+            }
+            best_pv = pv;
+            last_score = score;
+
+            if score.abs() > MATE_BOUND || stable >= self.stable_iterations {
+                break;
+            }
+        }
+
+        // Let later searches run once this one has stopped, whether by deadline or by the caller.
+        self.deadline = None;
+        self.timed_out.set(false);
+        self.stop = Arc::new(AtomicBool::new(false));
+        best_pv
+    }
+
+    /// Remember a quiet move that caused a beta cutoff, so it is tried early in sibling nodes,
+    /// and as a reply whenever the move it refuted is played again.
+    /// The quiet moves tried before it, in `tried`, failed to cut off, so their history is lowered.
+    fn update_quiet_stats(&mut self, m: Move, ply: i32, depth: i32, counter_slot: Option<(usize, usize)>, tried: &[Move]) {
+        if let Some((piece, dest)) = counter_slot {
+            self.countermoves[piece][dest] = Some(m);
+        }
+        if let Some(killers) = usize::try_from(ply).ok().and_then(|ply| self.killers.get_mut(ply)) {
+            if killers[0] != Some(m) {
+                killers[1] = killers[0];
+                killers[0] = Some(m);
+            }
+        }
+        let history = &mut self.history[usize::from(m.from.into_inner())][usize::from(m.dest.into_inner())];
+        *history = history.saturating_add(depth * depth);
+        for tried in tried {
+            let history = &mut self.history[usize::from(tried.from.into_inner())][usize::from(tried.dest.into_inner())];
+            *history = history.saturating_sub(depth * depth);
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        if self.stop.load(Ordering::Relaxed) || self.timed_out.get() {
+            return true;
+        }
+        // Reading the clock at every node would be slow, so only check it every so often.
+        if self.deadline.is_some_and(|deadline| self.nodes.is_multiple_of(1024) && Instant::now() >= deadline) {
+            self.timed_out.set(true);
+            return true;
+        }
+        false
+    }
+
+    /// A handle to the best root move found so far, which is updated mid-iteration whenever it changes.
+    /// Take it before moving the search to another thread, so `stop` can answer with a move at once.
+    pub fn best_move(&self) -> Arc<BestMove> {
+        Arc::clone(&self.best_move)
+    }
+
+    /// The static evaluation of a position from the side to move's point of view, without searching.
+    pub fn static_eval(&self, board: &Board) -> i32 {
+        self.eval.evaluate(board, &self.eval.eval(board))
+    }
+
+    /// Set the repetition keys of the positions played before the one to be searched, oldest first, so that root
+    /// moves repeating the game are scored as draws.
+    pub fn set_game_history(&mut self, history: &[u64]) {
+        self.game_history = history.to_vec();
+    }
+
+    /// Forget all previously-searched positions.
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
     }
 
     pub fn nodes(&self) -> u64 {
@@ -127,7 +821,488 @@ impl Search {
         self.qnodes
     }
 
+    /// Total time spent searching, including quiescence search.
+    pub fn search_time(&self) -> Duration {
+        self.search_time
+    }
+
+    /// Total time spent in quiescence search, estimated from the share of quiescence nodes.
+    pub fn quiesce_time(&self) -> Duration {
+        self.quiesce_time
+    }
+
     pub fn from_tuning_weights(&mut self, weights: &[i32]) {
         self.eval.from_tuning_weights(weights);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{coordinate_key, Search, SearchOptions, MATE_BOUND, MATE_VALUE, MAX_DEPTH, MAX_PLY};
+    use crate::eval::{Eval, MAX_EVAL};
+    use dorpsgek_movegen::{Board, Move};
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+    use tinyvec::ArrayVec;
+
+    #[test]
+    fn quiescence_finds_quiet_mate() {
+        // Ra8 is mate, but is not a capture, so it is beyond the horizon of a depth-zero search.
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+
+        let mut pv = ArrayVec::new();
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 10, ..SearchOptions::default() });
+        assert!(search.search_root(&board, 0, &mut pv) < MATE_BOUND);
+
+        let mut search = Search::with_options(SearchOptions {
+            tt_size: 1 << 10,
+            quiescence_checks: true,
+            ..SearchOptions::default()
+        });
+        assert!(search.search_root(&board, 0, &mut pv) > MATE_BOUND);
+    }
+
+    #[test]
+    fn quiescence_does_not_stand_pat_in_check() {
+        // White is a queen up, but back-rank mated; standing pat would call this winning.
+        let board = Board::from_fen("6k1/Q7/8/8/8/8/5PPP/3r2K1 w - - 0 1").unwrap();
+
+        let mut pv = ArrayVec::new();
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 10, ..SearchOptions::default() });
+        assert!(search.search_root(&board, 0, &mut pv) < -MATE_BOUND);
+
+        // With the queen on the d-file, the rook can be captured instead.
+        let board = Board::from_fen("6k1/8/8/8/3Q4/8/5PPP/3r2K1 w - - 0 1").unwrap();
+        assert!(search.search_root(&board, 0, &mut pv) > 0);
+    }
+
+    #[test]
+    fn extreme_material_is_not_a_mate_score() {
+        // Nine queens and a full set of pieces against a bare king, from both sides.
+        let eval = Eval::new();
+        for fen in ["k7/8/QQQQQQQQ/Q7/8/8/8/RNB1KBNR b - - 0 1", "rnb1kbnr/8/8/8/q7/qqqqqqqq/8/K7 w - - 0 1"] {
+            let board = Board::from_fen(fen).unwrap();
+            let score = eval.evaluate(&board, &eval.eval(&board));
+            assert!(score.abs() > MAX_EVAL / 2 && score.abs() < MAX_EVAL);
+            assert!(score.abs() < MATE_BOUND);
+        }
+    }
+
+    #[test]
+    fn search_stops_at_max_ply() {
+        // Either queen can keep checking the bare king opposite, giving the extensions plenty of lines to follow.
+        let board = Board::from_fen("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let options = SearchOptions {
+            tt_size: 1 << 16,
+            quiescence_checks: true,
+            singular_extensions: true,
+            recapture_extensions: true,
+            ..SearchOptions::default()
+        };
+        let mut search = Search::with_options(options);
+        let static_eval = search.static_eval(&board);
+        let eval = search.eval.eval(&board);
+
+        // At the limit, search and quiescence both return the static evaluation rather than going deeper.
+        let mut pv = ArrayVec::new();
+        assert_eq!(search.search(&board, 8, -100_000, 100_000, &eval, &mut pv, MATE_VALUE - MAX_PLY, None), static_eval);
+        assert!(pv.is_empty());
+        assert_eq!(search.quiesce(&board, -100_000, 100_000, &eval, 0, MATE_VALUE - MAX_PLY), static_eval);
+        assert_eq!(search.nodes() + search.qnodes(), 0);
+
+        // Close to the limit, the search still finishes.
+        let score = search.search(&board, 8, -100_000, 100_000, &eval, &mut pv, MATE_VALUE - MAX_PLY + 4, None);
+        assert!(score.abs() < MATE_BOUND);
+
+        let mut pv = ArrayVec::new();
+        search.search_root(&board, 8, &mut pv);
+        assert!(!pv.is_empty());
+    }
+
+    #[test]
+    fn static_eval_of_symmetric_position_is_level() {
+        let search = Search::new();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4",
+        ] {
+            // Only the tempo separates the sides.
+            assert!(search.static_eval(&Board::from_fen(fen).unwrap()).abs() < 25);
+        }
+
+        // The score is for the side to move.
+        let white = search.static_eval(&Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap());
+        let black = search.static_eval(&Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap());
+        assert!(white > 500);
+        assert!(black < -500);
+    }
+
+    #[test]
+    fn countermoves_are_recorded() {
+        let board = Board::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8").unwrap();
+        let count = |search: &Search| search.countermoves.iter().flatten().filter(|m| m.is_some()).count();
+
+        for countermoves in [false, true] {
+            let mut pv = ArrayVec::new();
+            let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, countermoves, ..SearchOptions::default() });
+            search.search_root(&board, 5, &mut pv);
+            assert_eq!(count(&search) > 0, countermoves);
+        }
+    }
+
+    #[test]
+    fn previous_pv_is_searched_first() {
+        // A tiny hash table loses the PV between iterations, so ordering must come from the saved PV instead.
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let options = SearchOptions { tt_size: 1 << 4, ..SearchOptions::default() };
+
+        let mut cold = Search::with_options(options);
+        let mut warm = Search::with_options(options);
+        for depth in 1..=6 {
+            let mut pv = ArrayVec::new();
+            cold.last_pv.clear();
+            cold.search_root(&board, depth, &mut pv);
+            warm.search_root(&board, depth, &mut pv);
+            assert!(warm.last_pv == pv);
+        }
+        assert!(warm.nodes() < cold.nodes(), "{} >= {}", warm.nodes(), cold.nodes());
+    }
+
+    #[test]
+    fn stalemate_is_a_draw() {
+        // Qg6 would stalemate; Qh6 mates.
+        let board = Board::from_fen("7k/5K2/4Q3/8/8/8/8/8 w - - 0 1").unwrap();
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, ..SearchOptions::default() });
+
+        let stalemate = Board::from_fen("7k/5K2/6Q1/8/8/8/8/8 b - - 1 1").unwrap();
+        let mut pv = ArrayVec::new();
+        assert_eq!(search.search_root(&stalemate, 0, &mut pv), 0);
+
+        for depth in 1..=3 {
+            assert!(search.search_root(&board, depth, &mut pv) > MATE_BOUND);
+            assert!(!board.make(pv[0]).is_stalemate());
+        }
+    }
+
+    #[test]
+    fn equal_moves_break_ties_by_coordinate() {
+        // Ra8 and Re8 both mate; so do Rh8 and Re8 in the mirrored position.
+        for (fen, best) in [("6k1/5ppp/8/8/8/8/8/R3R1K1 w - - 0 1", "a1a8"), ("1k6/ppp5/8/8/8/8/8/1K1R3R w - - 0 1", "d1d8")] {
+            let board = Board::from_fen(fen).unwrap();
+            let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, ..SearchOptions::default() });
+            let mut first = ArrayVec::new();
+            search.search_root(&board, 3, &mut first);
+            assert_eq!(first[0].to_string(), best);
+
+            // A second search starts with a warm hash table and history, but must agree.
+            let mut second = ArrayVec::new();
+            search.search_root(&board, 3, &mut second);
+            assert_eq!(first.iter().map(ToString::to_string).collect::<Vec<_>>(), second.iter().map(ToString::to_string).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn coordinate_key_sorts_like_notation() {
+        // White has promotions, including capture-promotions, alongside ordinary and castling moves.
+        let board = Board::from_fen("r3k2r/1P4P1/8/8/8/8/1p4p1/R3K2R w KQkq - 0 1").unwrap();
+        let mut moves = ArrayVec::from([Move::default(); 256]);
+        moves.set_len(0);
+        board.generate(&mut moves);
+        let mut by_key = moves.to_vec();
+        by_key.sort_by_key(|&m| coordinate_key(m));
+        let mut by_text = moves.iter().map(ToString::to_string).collect::<Vec<_>>();
+        by_text.sort();
+        assert_eq!(by_key.iter().map(ToString::to_string).collect::<Vec<_>>(), by_text);
+    }
+
+    #[test]
+    fn search_is_fail_soft() {
+        // White is a queen up, so a null window around zero fails high, and the bound should say by how much.
+        let board = Board::from_fen("4k3/8/8/8/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 10, ..SearchOptions::default() });
+        let eval = search.eval.eval(&board);
+        let mut pv = ArrayVec::new();
+        assert!(search.search(&board, 3, -1, 0, &eval, &mut pv, MATE_VALUE, None) > 500);
+
+        // And from black's side, it fails low by as much.
+        let board = Board::from_fen("4k3/8/8/8/8/8/3Q4/4K3 b - - 0 1").unwrap();
+        let eval = search.eval.eval(&board);
+        assert!(search.search(&board, 3, -1, 0, &eval, &mut pv, MATE_VALUE, None) < -500);
+    }
+
+    #[test]
+    fn root_avoids_repetition_when_winning() {
+        // White is a rook up; whatever it would play normally, make that move repeat an earlier position.
+        let board = Board::from_fen("4k3/8/4K3/8/8/8/8/R7 w - - 10 40").unwrap();
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 12, ..SearchOptions::default() });
+        let mut pv = ArrayVec::new();
+        search.search_root(&board, 4, &mut pv);
+        let favourite = pv[0];
+
+        // The game reached the position after that move two plies ago; the key between is never matched.
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 12, ..SearchOptions::default() });
+        search.set_game_history(&[board.make(favourite).repetition_key(), 0]);
+        let score = search.search_root(&board, 4, &mut pv);
+        assert_ne!(pv[0].to_string(), favourite.to_string());
+        assert!(score > 300);
+    }
+
+    #[test]
+    fn late_move_pruning_keeps_best_move() {
+        // Kiwipete's bishop capture on a6 and the rook lift to f4 in the endgame are found either way,
+        // but pruning searches fewer nodes.
+        for (fen, best) in [
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "e2a6"),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", "b4f4"),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mut nodes = Vec::new();
+            for late_move_pruning in [false, true] {
+                let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, late_move_pruning, ..SearchOptions::default() });
+                let mut pv = ArrayVec::new();
+                for depth in 1..=6 {
+                    search.search_root(&board, depth, &mut pv);
+                }
+                assert_eq!(pv[0].to_string(), best);
+                nodes.push(search.nodes());
+            }
+            assert!(nodes[1] < nodes[0], "{:?}", nodes);
+        }
+    }
+
+    #[test]
+    fn internal_iterative_deepening_keeps_best_move() {
+        // Searching straight to depth 6 with an empty hash table finds the same moves either way,
+        // but with fewer nodes when the PV nodes first look for a move to try.
+        for (fen, best) in [
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "e2a6"),
+            ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", "b1c3"),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mut nodes = Vec::new();
+            for internal_iterative_deepening in [false, true] {
+                let mut search = Search::with_options(SearchOptions { internal_iterative_deepening, ..SearchOptions::default() });
+                let mut pv = ArrayVec::new();
+                search.search_root(&board, 6, &mut pv);
+                assert_eq!(pv[0].to_string(), best);
+                nodes.push(search.nodes() + search.qnodes());
+            }
+            assert!(nodes[1] < nodes[0], "{:?}", nodes);
+        }
+    }
+
+    #[test]
+    fn search_mate_finds_smothered_mate() {
+        // 1. Nh6+ Kh8 2. Qg8+ Rxg8 3. Nf7#.
+        let board = Board::from_fen("5rk1/5Npp/8/8/8/1Q6/6PP/6K1 w - - 0 1").unwrap();
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, ..SearchOptions::default() });
+
+        assert!(search.search_mate(&board, 2).is_none());
+        let line = search.search_mate(&board, 3).unwrap().iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(line, ["f7h6", "g8h8", "b3g8", "f8g8", "h6f7"]);
+    }
+
+    #[test]
+    fn analyze_stops_at_proven_mate() {
+        let board = Board::from_fen("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let mut iterations = Vec::new();
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, ..SearchOptions::default() });
+        let pv = search.analyze(&board, stop, |depth, score, _, _| iterations.push((depth, score)));
+
+        // White mates with a rook ladder; nothing stops the search except finding it.
+        // Null-move pruning can hide the shortest mate, so only check that the PV plays out the mate found.
+        let &(depth, score) = iterations.last().unwrap();
+        assert!(depth < MAX_DEPTH);
+        assert!(score > MATE_BOUND);
+        assert_eq!(pv.len() as i32, MATE_VALUE - score);
+    }
+
+    #[test]
+    fn best_move_is_readable_mid_search() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let mut search = Search::new();
+        let best_move = search.best_move();
+        assert!(best_move.get().is_none());
+
+        let handle = {
+            let board = board.clone();
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || search.analyze(&board, stop, |_, _, _, _| {}))
+        };
+
+        thread::sleep(Duration::from_millis(100));
+        let m = best_move.get().expect("a root move has been searched");
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+
+        let mut moves = ArrayVec::from([Move::default(); 256]);
+        moves.set_len(0);
+        board.generate(&mut moves);
+        assert!(moves.contains(&m));
+    }
+
+    #[test]
+    fn search_timed_stops_when_stable() {
+        // White is a rook and queen up with nothing to think about, so the best move settles quickly.
+        let board = Board::from_fen("4k3/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1").unwrap();
+        let budget = Duration::from_secs(60);
+        let options = SearchOptions { tt_size: 1 << 16, stable_iterations: 2, stable_margin: 50, ..SearchOptions::default() };
+
+        let start = Instant::now();
+        let mut iterations = 0;
+        let pv = Search::with_options(options).search_timed(&board, budget, Arc::new(AtomicBool::new(false)), |_, _, _, _| iterations += 1);
+        assert!(!pv.is_empty());
+        assert!(start.elapsed() < budget / 10);
+        assert!(iterations < MAX_DEPTH);
+    }
+
+    #[test]
+    fn search_timed_respects_budget() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let options = SearchOptions { tt_size: 1 << 16, stable_iterations: u32::MAX, ..SearchOptions::default() };
+
+        let start = Instant::now();
+        let pv = Search::with_options(options).search_timed(&board, Duration::from_millis(200), Arc::new(AtomicBool::new(false)), |_, _, _, _| {});
+        assert!(!pv.is_empty());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn search_timed_leaves_move_overhead() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let options = SearchOptions {
+            tt_size: 1 << 16,
+            stable_iterations: u32::MAX,
+            move_overhead_ms: 1_800,
+            ..SearchOptions::default()
+        };
+
+        // Only 200ms of the two seconds are left to search with.
+        let start = Instant::now();
+        let pv = Search::with_options(options).search_timed(&board, Duration::from_secs(2), Arc::new(AtomicBool::new(false)), |_, _, _, _| {});
+        assert!(!pv.is_empty());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn search_after_timeout() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let options = SearchOptions { tt_size: 1 << 16, stable_iterations: u32::MAX, ..SearchOptions::default() };
+        let mut search = Search::with_options(options);
+        search.search_timed(&board, Duration::from_millis(50), Arc::new(AtomicBool::new(false)), |_, _, _, _| {});
+
+        let mut pv = ArrayVec::new();
+        search.search_root(&board, 3, &mut pv);
+        assert!(!pv.is_empty());
+
+        let mate = Board::from_fen("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(search.search_mate(&mate, 1).map(|line| line.len()), Some(1));
+    }
+
+    #[test]
+    fn search_timed_can_be_stopped() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let options = SearchOptions { tt_size: 1 << 16, stable_iterations: u32::MAX, ..SearchOptions::default() };
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_at_depth = Arc::clone(&stop);
+
+        let start = Instant::now();
+        Search::with_options(options).search_timed(&board, Duration::from_secs(60), stop, |depth, _, _, _| {
+            if depth >= 2 {
+                stop_at_depth.store(true, Ordering::Relaxed);
+            }
+        });
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn iterations_report_branching_factor() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut search = Search::new();
+
+        let mut iterations = Vec::new();
+        let stop_at_depth = Arc::clone(&stop);
+        search.analyze(&board, stop, |depth, _, _, stats| {
+            iterations.push(*stats);
+            if depth >= 6 {
+                stop_at_depth.store(true, Ordering::Relaxed);
+            }
+        });
+
+        assert_eq!(iterations.len(), 6);
+        assert!(iterations[0].branching_factor.is_none());
+        for stats in &iterations[1..] {
+            let ebf = stats.branching_factor.unwrap();
+            assert!((1.0..40.0).contains(&ebf), "{:?}", iterations);
+        }
+        assert!(iterations.iter().all(|stats| stats.quiesce_time <= stats.elapsed));
+        assert!(search.quiesce_time() > Duration::ZERO);
+        assert!(search.quiesce_time() <= search.search_time());
+    }
+
+    #[test]
+    fn mates_lone_king_within_fifty_moves() {
+        for fen in ["8/8/8/3k4/8/8/8/4K2Q w - - 0 1", "8/8/8/3k4/8/8/8/4K2R w - - 0 1"] {
+            let mut board = Board::from_fen(fen).unwrap();
+            let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, ..SearchOptions::default() });
+
+            // Both sides play the best move of a shallow search until there are none left.
+            while board.halfmove_clock() < 100 {
+                let mut pv = ArrayVec::new();
+                search.search_root(&board, 8, &mut pv);
+                match pv.first() {
+                    Some(&m) => board = board.make(m),
+                    None => break,
+                }
+            }
+
+            let mut moves = ArrayVec::from([Move::default(); 256]);
+            moves.set_len(0);
+            board.generate(&mut moves);
+            assert!(moves.is_empty() && board.in_check(), "{} not mated by {}", fen, board);
+        }
+    }
+
+    #[test]
+    fn singular_extension_finds_mating_attack() {
+        // Bh2+ starts a mating attack that needs more than seven plies to see through; extending the forcing
+        // moves on the way finds it.
+        let board = Board::from_fen("3q1rk1/p4pp1/2pb3p/3p4/6Pr/1PNQ4/P1PB1PP1/4RRK1 b - - 0 1").unwrap();
+
+        for singular_extensions in [false, true] {
+            let mut pv = ArrayVec::new();
+            let mut search = Search::with_options(SearchOptions { tt_size: 1 << 18, singular_extensions, ..SearchOptions::default() });
+            let score = (1..=7).map(|depth| search.search_root(&board, depth, &mut pv)).last().unwrap();
+
+            assert_eq!(score > MATE_BOUND, singular_extensions);
+            assert_eq!(pv[0].to_string(), "d6h2");
+        }
+    }
+
+    #[test]
+    fn recapture_extension_sees_through_exchange() {
+        // Qxh7+ Kxh7 hxg6 is mate, but the forced recapture on h7 uses up a ply of a two-ply search.
+        let board = Board::from_fen("r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - 0 1").unwrap();
+
+        for recapture_extensions in [false, true] {
+            let mut pv = ArrayVec::new();
+            let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, recapture_extensions, ..SearchOptions::default() });
+            let score = (1..=2).map(|depth| search.search_root(&board, depth, &mut pv)).last().unwrap();
+
+            assert_eq!(score > MATE_BOUND, recapture_extensions);
+            assert_eq!(pv[0].to_string() == "h6h7", recapture_extensions);
+        }
+    }
+}
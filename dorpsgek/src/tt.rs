@@ -0,0 +1,249 @@
+use std::convert::TryFrom;
+
+use dorpsgek_movegen::Move;
+
+/// The kind of bound a stored score represents.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The score is a lower bound (the search failed high).
+    Lower,
+    /// The score is an upper bound (the search failed low).
+    Upper,
+}
+
+/// How the transposition table decides which entry to overwrite.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReplacementPolicy {
+    /// Always overwrite the existing entry.
+    AlwaysReplace,
+    /// Only overwrite the existing entry if the new entry was searched at least as deep.
+    DepthPreferred,
+    /// Buckets of two entries: one depth-preferred, one always-replace.
+    #[default]
+    TwoTier,
+}
+
+/// A transposition table entry.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Entry {
+    pub key: u64,
+    pub m: Option<Move>,
+    pub score: i32,
+    pub depth: i32,
+    pub bound: Bound,
+}
+
+/// An entry as stored in the table, packed into 16 bytes.
+#[derive(Copy, Clone)]
+struct Slot {
+    key: u64,
+    /// The packed move, or `NO_MOVE`.
+    m: u16,
+    score: i16,
+    depth: i16,
+    bound: Bound,
+}
+
+/// A packed move that `Move::unpack` rejects, standing for no move.
+pub const NO_MOVE: u16 = 0xFFFF;
+
+impl From<Entry> for Slot {
+    fn from(entry: Entry) -> Self {
+        Self {
+            key: entry.key,
+            m: entry.m.map_or(NO_MOVE, Move::pack),
+            // Scores lie within mate values and depths within the maximum depth, so neither is truncated.
+            score: i16::try_from(entry.score).expect("score fits in 16 bits"),
+            depth: i16::try_from(entry.depth).expect("depth fits in 16 bits"),
+            bound: entry.bound,
+        }
+    }
+}
+
+impl From<Slot> for Entry {
+    fn from(slot: Slot) -> Self {
+        Self {
+            key: slot.key,
+            m: Move::unpack(slot.m),
+            score: i32::from(slot.score),
+            depth: i32::from(slot.depth),
+            bound: slot.bound,
+        }
+    }
+}
+
+/// A hash table of previously-searched positions.
+pub struct TranspositionTable {
+    entries: Vec<Option<Slot>>,
+    policy: ReplacementPolicy,
+}
+
+impl TranspositionTable {
+    /// Create a table holding `size` entries, rounded up to a multiple of two.
+    pub fn new(size: usize, policy: ReplacementPolicy) -> Self {
+        let size = (size.max(2) + 1) & !1;
+        Self {
+            entries: vec![None; size],
+            policy,
+        }
+    }
+
+    /// Remove all entries from the table.
+    pub fn clear(&mut self) {
+        for entry in &mut self.entries {
+            *entry = None;
+        }
+    }
+
+    /// The index of the first slot a key may be stored in.
+    fn index(&self, key: u64) -> usize {
+        match self.policy {
+            ReplacementPolicy::AlwaysReplace | ReplacementPolicy::DepthPreferred => {
+                (key % self.entries.len() as u64) as usize
+            }
+            ReplacementPolicy::TwoTier => 2 * (key % (self.entries.len() / 2) as u64) as usize,
+        }
+    }
+
+    /// Look up an entry by its key.
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        let index = self.index(key);
+        let slots = match self.policy {
+            ReplacementPolicy::AlwaysReplace | ReplacementPolicy::DepthPreferred => 1,
+            ReplacementPolicy::TwoTier => 2,
+        };
+
+        self.entries[index..index + slots]
+            .iter()
+            .flatten()
+            .find(|slot| slot.key == key)
+            .map(|&slot| Entry::from(slot))
+    }
+
+    /// Store an entry, subject to the replacement policy.
+    pub fn store(&mut self, entry: Entry) {
+        let index = self.index(entry.key);
+        let entry = Slot::from(entry);
+        match self.policy {
+            ReplacementPolicy::AlwaysReplace => self.entries[index] = Some(entry),
+            ReplacementPolicy::DepthPreferred => {
+                if let Some(old) = self.entries[index] {
+                    if old.key != entry.key && old.depth > entry.depth {
+                        return;
+                    }
+                }
+                self.entries[index] = Some(entry);
+            }
+            ReplacementPolicy::TwoTier => {
+                // The first slot of a bucket keeps the deepest entry; the second takes everything else.
+                match self.entries[index] {
+                    Some(old) if old.key != entry.key && old.depth > entry.depth => {
+                        self.entries[index + 1] = Some(entry);
+                    }
+                    // A displaced entry for another position moves down to the second slot rather than being lost.
+                    Some(old) if old.key != entry.key => {
+                        self.entries[index + 1] = Some(old);
+                        self.entries[index] = Some(entry);
+                    }
+                    _ => {
+                        if self.entries[index + 1].is_some_and(|old| old.key == entry.key) {
+                            self.entries[index + 1] = None;
+                        }
+                        self.entries[index] = Some(entry);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bound, Entry, ReplacementPolicy, Slot, TranspositionTable};
+    use dorpsgek_movegen::{Board, Move};
+    use tinyvec::ArrayVec;
+
+    fn entry(key: u64, depth: i32, bound: Bound) -> Entry {
+        Entry {
+            key,
+            m: None,
+            score: key as i32,
+            depth,
+            bound,
+        }
+    }
+
+    #[test]
+    fn always_replace_keeps_newest() {
+        let mut tt = TranspositionTable::new(16, ReplacementPolicy::AlwaysReplace);
+        tt.store(entry(3, 10, Bound::Exact));
+        tt.store(entry(19, 1, Bound::Upper));
+        assert!(tt.probe(3).is_none());
+        assert!(tt.probe(19) == Some(entry(19, 1, Bound::Upper)));
+    }
+
+    #[test]
+    fn depth_preferred_keeps_deepest() {
+        let mut tt = TranspositionTable::new(16, ReplacementPolicy::DepthPreferred);
+        tt.store(entry(3, 10, Bound::Lower));
+        tt.store(entry(19, 1, Bound::Upper));
+        assert!(tt.probe(3) == Some(entry(3, 10, Bound::Lower)));
+        assert!(tt.probe(19).is_none());
+
+        // Same-position entries are always refreshed.
+        tt.store(entry(3, 2, Bound::Exact));
+        assert!(tt.probe(3) == Some(entry(3, 2, Bound::Exact)));
+    }
+
+    #[test]
+    fn two_tier_keeps_deepest_and_newest() {
+        let mut tt = TranspositionTable::new(16, ReplacementPolicy::TwoTier);
+
+        // Fill every bucket with a deep entry, then flood it with shallow entries.
+        for key in 0..8 {
+            tt.store(entry(key, 10, Bound::Lower));
+        }
+        for key in 8..64 {
+            tt.store(entry(key, 1, Bound::Upper));
+        }
+
+        for key in 0..8 {
+            assert!(tt.probe(key) == Some(entry(key, 10, Bound::Lower)));
+        }
+        for key in 56..64 {
+            assert!(tt.probe(key) == Some(entry(key, 1, Bound::Upper)));
+        }
+        for key in 8..56 {
+            assert!(tt.probe(key).is_none());
+        }
+    }
+
+    #[test]
+    fn two_tier_demotes_displaced_entry() {
+        let mut tt = TranspositionTable::new(16, ReplacementPolicy::TwoTier);
+        tt.store(entry(3, 5, Bound::Lower));
+        tt.store(entry(11, 8, Bound::Exact));
+        assert!(tt.probe(11) == Some(entry(11, 8, Bound::Exact)));
+        assert!(tt.probe(3) == Some(entry(3, 5, Bound::Lower)));
+    }
+
+    #[test]
+    fn entries_are_packed() {
+        assert_eq!(std::mem::size_of::<Option<Slot>>(), 16);
+
+        // Moves and scores survive the trip through the table.
+        let board = Board::from_fen("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1").unwrap();
+        let mut moves = ArrayVec::from([Move::default(); 256]);
+        moves.set_len(0);
+        board.generate(&mut moves);
+
+        let mut tt = TranspositionTable::new(16, ReplacementPolicy::AlwaysReplace);
+        for m in moves {
+            let entry = Entry { key: 5, m: Some(m), score: -29_990, depth: 7, bound: Bound::Lower };
+            tt.store(entry);
+            assert!(tt.probe(5) == Some(entry));
+        }
+    }
+}
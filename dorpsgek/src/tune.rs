@@ -1,11 +1,11 @@
 use std::{convert::TryInto, io::Read};
 
 use dorpsgek_movegen::{Board, Colour, Move, Piece, Square};
-use rand::prelude::*;
+use rand::{prelude::*, rngs::StdRng};
 use revad::tape::{Tape, Var};
 use tinyvec::ArrayVec;
 
-use crate::Search;
+use crate::{Search, SearchOptions};
 
 #[derive(Clone)]
 pub struct EvalState<'a> {
@@ -93,23 +93,161 @@ impl<'a> Eval<'a> {
         let mut score = EvalState::new(tape);
 
         for piece in board.pieces() {
-            let square = board.square_of_piece(piece);
-            score.add_piece(self, board.piece_from_bit(piece), square, piece.colour());
+            let (piece, colour, square) = board.piece_info(piece);
+            score.add_piece(self, piece, square, colour);
         }
 
         (tape.var(0.00255) * score.get(tape, board.side())).tanh()
     }
+
+    /// The expected game result from White's view, `sigmoid(eval / k)`, for fitting to game outcomes.
+    pub fn expected_result(&'a self, board: &Board, tape: &'a Tape, k: f64) -> Var<'a> {
+        // Subtraction on the tape records the wrong derivative for its left operand,
+        // so sum each side's terms separately and only negate Black's at the end.
+        let mut mg = [tape.var(0.0); 2];
+        let mut eg = [tape.var(0.0); 2];
+        let mut phase = 0.0;
+
+        for piece in board.pieces() {
+            let (kind, colour, square) = board.piece_info(piece);
+            let kind = kind as usize;
+            let (side, square) = if colour == Colour::White { (0, square) } else { (1, square.flip()) };
+            mg[side] = mg[side] + self.pst_mg[kind][square.into_inner() as usize] + self.mat_mg[kind];
+            eg[side] = eg[side] + self.pst_eg[kind][square.into_inner() as usize] + self.mat_eg[kind];
+            phase += self.phase[kind].value();
+        }
+
+        let score = (mg[0] + -mg[1]) * tape.var(phase / 24.0) + (eg[0] + -eg[1]) * tape.var((24.0 - phase) / 24.0);
+
+        // The tape has no sigmoid, so splice in a node with its value and its slope, s(1 - s)/k.
+        let s = 1.0 / (1.0 + (-score.value() / k).exp());
+        tape.var(s * (1.0 - s) / k) * (score + -tape.var(score.value())) + tape.var(s)
+    }
+}
+
+/// The optimiser used to apply gradients to the weights.
+#[derive(Clone, Copy, Debug)]
+pub enum TuneMethod {
+    /// Plain stochastic gradient descent with a fixed step size.
+    Sgd { lr: f64 },
+    /// Adam, with bias-corrected first and second moment estimates.
+    Adam { lr: f64, beta1: f64, beta2: f64, epsilon: f64 },
+}
+
+impl Default for TuneMethod {
+    fn default() -> Self {
+        Self::Adam { lr: 1.0, beta1: 0.9, beta2: 0.999, epsilon: 1e-8 }
+    }
+}
+
+impl TuneMethod {
+    /// Return the new value of a weight given its gradient, updating its moment estimates.
+    /// `t` is the one-based step number, used for Adam's bias correction.
+    fn update(self, weight: f64, grad: f64, m_t: &mut f64, v_t: &mut f64, t: i32) -> f64 {
+        match self {
+            Self::Sgd { lr } => lr.mul_add(-grad, weight),
+            Self::Adam { lr, beta1, beta2, epsilon } => {
+                *m_t = beta1.mul_add(*m_t, (1.0 - beta1)*grad);
+                *v_t = beta2.mul_add(*v_t, (1.0 - beta2)*grad*grad);
+
+                let m_hat = *m_t / (1.0 - beta1.powi(t));
+                let v_hat = *v_t / (1.0 - beta2.powi(t));
+
+                weight - lr * m_hat / (v_hat.sqrt() + epsilon)
+            }
+        }
+    }
+}
+
+/// Tuner configuration.
+#[derive(Clone, Copy, Debug)]
+pub struct TuneOptions {
+    /// The largest permitted L2 norm of a gradient; larger gradients are scaled down to it.
+    pub max_grad_norm: f64,
+    /// The optimiser to use.
+    pub method: TuneMethod,
+    /// The number of positions whose gradients are accumulated before each optimiser step.
+    pub batch_size: usize,
+    /// The number of optimiser steps in an epoch.
+    pub batches: usize,
+    /// The number of random moves played from each position before tuning on it, for variety.
+    pub random_plies: usize,
+    /// The coefficient of an L2 penalty on how far the square-table weights have moved from their starting values,
+    /// which keeps them from drifting to extremes over long runs. Zero disables it.
+    pub regularization: f64,
+}
+
+impl Default for TuneOptions {
+    fn default() -> Self {
+        Self {
+            max_grad_norm: 1.0,
+            method: TuneMethod::default(),
+            batch_size: 16,
+            batches: 100,
+            random_plies: 1,
+            regularization: 0.0,
+        }
+    }
+}
+
+/// Parse an EPD line annotated with a game result from White's view, such as
+/// `rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - [0.5]`.
+/// Returns `None` if the result is missing or outside `0.0..=1.0`.
+pub fn parse_labelled(line: &str) -> Option<(Board, f64)> {
+    let open = line.rfind('[')?;
+    let close = open + line[open..].find(']')?;
+    let result = line[open + 1..close].trim().parse::<f64>().ok().filter(|result| (0.0..=1.0).contains(result))?;
+    let board = Board::from_fen(line[..open].trim())?;
+    Some((board, result))
+}
+
+/// Play `plies` random legal moves from `board`, retrying lines that end the game early.
+/// If no such line can be found, `board` is returned unchanged.
+fn random_opening<R: Rng>(board: &Board, plies: usize, rng: &mut R) -> Board {
+    'attempt: for _ in 0..100 {
+        let mut line = board.clone();
+        for ply in 0..=plies {
+            let moves: [Move; 256] = [Move::default(); 256];
+            let mut moves = ArrayVec::from(moves);
+            moves.set_len(0);
+            line.generate(&mut moves);
+
+            // Every position on the line, including the last, needs a legal move so the game isn't over.
+            let m = match moves.iter().choose(rng) {
+                Some(&m) => m,
+                None => continue 'attempt,
+            };
+            if ply == plies {
+                return line;
+            }
+            line = line.make(m);
+        }
+    }
+    board.clone()
 }
 
 pub struct Tune<'a> {
     learning_rate: f64,
     weights: [Var<'a>; 780],
+    /// The starting weights, which regularization pulls back towards.
+    anchor: [f64; 780],
     m_t: [f64; 780],
     v_t: [f64; 780],
+    rng: StdRng,
+    options: TuneOptions,
 }
 
 impl<'a> Tune<'a> {
     pub fn new(tape: &'a Tape) -> Self {
+        Self::with_rng(tape, StdRng::from_entropy())
+    }
+
+    /// Create a tuner whose random choices are determined by `seed`, for repeatable experiments.
+    pub fn new_seeded(tape: &'a Tape, seed: u64) -> Self {
+        Self::with_rng(tape, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(tape: &'a Tape, rng: StdRng) -> Self {
         let weights = [
             // Midgame Material
             tape.var(100_f64), tape.var(300_f64), tape.var(300_f64), tape.var(500_f64), tape.var(900_f64),  tape.var(0_f64),
@@ -229,19 +367,33 @@ impl<'a> Tune<'a> {
             //tape.var(0_f64), tape.var(1_f64), tape.var(1_f64), tape.var(2_f64), tape.var(4_f64), tape.var(0_f64),
         ];
 
+        let mut anchor = [0.0; 780];
+        for (anchor, weight) in anchor.iter_mut().zip(&weights) {
+            *anchor = weight.value();
+        }
+
         Self {
             learning_rate: 0.7,
             weights,
+            anchor,
             m_t: [0.0; 780],
             v_t: [0.0; 780],
+            rng,
+            options: TuneOptions::default(),
         }
     }
 
+    pub fn set_options(&mut self, options: TuneOptions) {
+        self.options = options;
+    }
+
+    /// The weights and optimiser moments, for saving between epochs.
     pub fn get_state(&self) -> ([f64; 780], [f64; 780], [f64; 780]) {
         let weights = self.weights.iter().map(|var| var.value()).collect::<Vec<_>>();
         (weights.as_slice().try_into().unwrap(), self.m_t, self.v_t)
     }
 
+    /// Restore weights and optimiser moments saved by `get_state`.
     pub fn set_state(&mut self, tape: &'a Tape, weights: &[f64], m_t: &[f64], v_t: &[f64]) {
         for i in 0..self.weights.len() {
             self.weights[i] = tape.var(weights[i]);
@@ -250,6 +402,7 @@ impl<'a> Tune<'a> {
         }
     }
 
+    /// Print the weights as Rust source in the layout of the evaluation tables.
     pub fn dump(&self) {
         // Discover and remove means
         let mut mean_mg = [0.0; 6];
@@ -406,132 +559,411 @@ impl<'a> Tune<'a> {
     }
 
     pub fn tune(&mut self, tape: &'a Tape, boards: &[Board], epoch: i32) {
-        for n in 1..=100 {
-            let mut mean_mg = [0.0; 6];
-            let mut mean_eg = [0.0; 6];
-
-            mean_mg[0] = self.weights[12..75].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_mg[1] = self.weights[75..139].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_mg[2] = self.weights[139..203].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_mg[3] = self.weights[203..267].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_mg[4] = self.weights[267..331].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_mg[5] = self.weights[331..395].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-
-            mean_eg[0] = self.weights[395..459].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_eg[1] = self.weights[459..523].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_eg[2] = self.weights[523..587].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_eg[3] = self.weights[587..651].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_eg[4] = self.weights[651..715].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-            mean_eg[5] = self.weights[715..779].iter().map(|v| v.value()).sum::<f64>() / 64.0;
-
-            print!("Iter {:>5}: ", epoch*100 + n);
-            print!("piece values: [");
-            for (index, w) in self.weights[0..5].iter().enumerate() {
-                print!("{:>4.0} ", w.value() - mean_mg[index]);
-            }
-            print!("] [");
-            for (index, w) in self.weights[6..11].iter().enumerate() {
-                print!("{:>4.0} ", w.value() - mean_eg[index]);
+        for n in 1..=self.options.batches as i32 {
+            self.print_progress(epoch*self.options.batches as i32 + n);
+
+            let plies = self.options.random_plies;
+            let batch = boards
+                .iter()
+                .choose_multiple(&mut self.rng, self.options.batch_size)
+                .into_iter()
+                .map(|board| random_opening(board, plies, &mut self.rng))
+                .collect::<Vec<_>>();
+            let mut search = self.search();
+            let eval = Eval::from_tuning_weights(tape, &self.weights);
+
+            let mut loss = tape.var(0.0);
+            let mut error = 0.0;
+            for board in &batch {
+                let (board_loss, board_error) = self.temporal_differences(tape, &eval, &mut search, board);
+                loss = loss + board_loss;
+                error += board_error;
             }
-            print!("]; ");
 
-            let board = boards.iter().choose(&mut thread_rng()).unwrap();
+            let scale = 1.0 / batch.len().max(1) as f64;
+            loss = tape.var(scale) * loss + self.regularization(tape);
 
-            // Make a random legal move on the board
-            let moves: [Move; 256] = [Move::default(); 256];
-            let mut moves = ArrayVec::from(moves);
-            moves.set_len(0);
-            board.generate(&mut moves);
-            let m = *moves.iter().choose(&mut thread_rng()).unwrap();
-            let board = board.make(m);
+            println!("err: {} ", error * scale);
 
-            // Initialise the search.
-            let mut weights = Vec::new();
-            for w in &mut self.weights {
-                weights.push(w.value() as i32);
-            }
-            let mut s = Search::new();
-            s.from_tuning_weights(&weights);
+            let grad = loss.grad();
+            let mut grads = self.weights.iter().map(|weight| grad.wrt(*weight)).collect::<Vec<_>>();
+            self.step(tape, &mut grads, n);
+        }
+    }
+
+    /// Fit the weights to game results by minimising the mean squared error between `sigmoid(eval / k)`
+    /// and each position's result, as in Texel's tuning method. Results are from White's view.
+    pub fn tune_texel(&mut self, tape: &'a Tape, positions: &[(Board, f64)], k: f64, epoch: i32) {
+        for n in 1..=self.options.batches as i32 {
+            self.print_progress(epoch*self.options.batches as i32 + n);
 
-            // Then collect temporal differences.
+            let batch = positions.iter().choose_multiple(&mut self.rng, self.options.batch_size);
             let eval = Eval::from_tuning_weights(tape, &self.weights);
 
-            let mut scores = Vec::new();
-            let mut diffs = Vec::new();
+            let mut loss = tape.var(0.0);
+            for (board, result) in &batch {
+                let error = eval.expected_result(board, tape, k) + tape.var(-result);
+                loss = loss + error * error;
+            }
 
-            let mut last_pv = ArrayVec::new();
-            last_pv.set_len(0);
+            let scale = 1.0 / batch.len().max(1) as f64;
+            loss = tape.var(scale) * loss;
 
-            scores.push(eval.gradient(&board, tape));
-            diffs.push(tape.var(0.0));
+            println!("err: {} ", loss.value());
+            loss = loss + self.regularization(tape);
 
-            for position in 0..12 {
-                let mut pv = ArrayVec::new();
-                pv.set_len(0);
-                let score = s.search_root(&board, 2, &mut pv);
+            let grad = loss.grad();
+            let mut grads = self.weights.iter().map(|weight| grad.wrt(*weight)).collect::<Vec<_>>();
+            self.step(tape, &mut grads, n);
+        }
+    }
 
-                let mut pv_board = board.clone();
-                for m in pv {
-                    pv_board = pv_board.make(m);
-                }
+    /// The mean squared error between `sigmoid(eval / k)` and the results of `positions` under the current weights.
+    pub fn texel_error(&self, tape: &'a Tape, positions: &[(Board, f64)], k: f64) -> f64 {
+        let eval = Eval::from_tuning_weights(tape, &self.weights);
+        let error = positions.iter().map(|(board, result)| (eval.expected_result(board, tape, k).value() - result).powi(2)).sum::<f64>();
+        error / positions.len().max(1) as f64
+    }
 
-                if pv.is_empty() {
-                    if score == 0 {
-                        scores.push(tape.var(0.0));
-                    } else if score > 0 {
-                        scores.push(tape.var(1.0));
-                    } else {
-                        scores.push(tape.var(-1.0));
-                    }
-                } else {
-                    scores.push(eval.gradient(&pv_board, tape));
-                }
+    /// Print the iteration number and the piece values, less the mean of their square tables.
+    fn print_progress(&self, iter: i32) {
+        let mut mean_mg = [0.0; 6];
+        let mut mean_eg = [0.0; 6];
 
-                let diff = scores[scores.len() - 2] - scores[scores.len() - 1];
-                if diff.value() > 0.0 && !last_pv.is_empty() && pv[0] != last_pv[1] {
+        mean_mg[0] = self.weights[12..75].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_mg[1] = self.weights[75..139].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_mg[2] = self.weights[139..203].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_mg[3] = self.weights[203..267].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_mg[4] = self.weights[267..331].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_mg[5] = self.weights[331..395].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+
+        mean_eg[0] = self.weights[395..459].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_eg[1] = self.weights[459..523].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_eg[2] = self.weights[523..587].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_eg[3] = self.weights[587..651].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_eg[4] = self.weights[651..715].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+        mean_eg[5] = self.weights[715..779].iter().map(|v| v.value()).sum::<f64>() / 64.0;
+
+        print!("Iter {:>5}: ", iter);
+        print!("piece values: [");
+        for (index, w) in self.weights[0..5].iter().enumerate() {
+            print!("{:>4.0} ", w.value() - mean_mg[index]);
+        }
+        print!("] [");
+        for (index, w) in self.weights[6..11].iter().enumerate() {
+            print!("{:>4.0} ", w.value() - mean_eg[index]);
+        }
+        print!("]; ");
+    }
+
+    /// The mean squared temporal difference over `boards` under the current weights.
+    pub fn mean_error(&self, tape: &'a Tape, boards: &[Board]) -> f64 {
+        let mut search = self.search();
+        let eval = Eval::from_tuning_weights(tape, &self.weights);
+        let error = boards.iter().map(|board| self.temporal_differences(tape, &eval, &mut search, board).1).sum::<f64>();
+        error / boards.len().max(1) as f64
+    }
+
+    /// A searcher using the current weights.
+    fn search(&self) -> Search {
+        let weights = self.weights.iter().map(|w| w.value() as i32).collect::<Vec<_>>();
+        let mut search = Search::with_options(SearchOptions { tt_size: 1 << 16, ..SearchOptions::default() });
+        search.from_tuning_weights(&weights);
+        search
+    }
+
+    /// Play out the principal variation from `board`, scoring the leaf of each shallow search.
+    /// Returns the TD(lambda) objective, whose gradient is the weight update, and the mean squared temporal difference.
+    fn temporal_differences(&self, tape: &'a Tape, eval: &'a Eval<'a>, search: &mut Search, board: &Board) -> (Var<'a>, f64) {
+        let mut board = board.clone();
+        let mut scores = Vec::new();
+        let mut diffs = Vec::new();
+
+        let mut last_pv = ArrayVec::new();
+        last_pv.set_len(0);
+
+        for _ in 0..12 {
+            let mut pv = ArrayVec::new();
+            pv.set_len(0);
+            let score = search.search_root(&board, 2, &mut pv);
+
+            let mut pv_board = board.clone();
+            for m in pv {
+                pv_board = pv_board.make(m);
+            }
+
+            // Scores are from the side to move, so flip them to keep every score from White's view.
+            let sign = |side| if side == Colour::White { 1.0 } else { -1.0 };
+            if pv.is_empty() {
+                scores.push(tape.var(sign(board.side()) * f64::from(score.signum())));
+            } else {
+                scores.push(tape.var(sign(pv_board.side())) * eval.gradient(&pv_board, tape));
+            }
+
+            if scores.len() >= 2 {
+                let diff = scores[scores.len() - 1].value() - scores[scores.len() - 2].value();
+                if sign(board.side()) * diff > 0.0 && !last_pv.is_empty() && pv.first() != last_pv.get(1) {
                     // Last move was a blunder; don't learn from it.
-                    diffs.push(tape.var(0.0));
+                    diffs.push(0.0);
                 } else {
                     diffs.push(diff);
                 }
+            }
 
-                if pv.is_empty() {
-                    break;
-                }
+            if pv.is_empty() {
+                break;
+            }
 
-                last_pv = pv;
+            board = board.make(pv[0]);
+            last_pv = pv;
+        }
+
+        // Move each score towards the discounted sum of the differences that follow it.
+        let mut objective = tape.var(0.0);
+        for (n, score) in scores.iter().enumerate().take(diffs.len()) {
+            let mut target = 0.0;
+            for (m, diff) in diffs.iter().enumerate().skip(n) {
+                target += diff * self.learning_rate.powi((m - n) as i32);
             }
+            objective = objective + tape.var(-target) * *score;
+        }
 
-            let mut sum1 = tape.var(0.0);
+        let error = diffs.iter().map(|diff| diff * diff).sum::<f64>() / diffs.len().max(1) as f64;
+        (objective, error)
+    }
 
-            for n in 1..scores.len() {
-                let mut sum2 = tape.var(0.0);
-                for (m, diff) in diffs.iter().enumerate().take(scores.len()).skip(n) {
-                    sum2 = sum2 + tape.var(diff.value() * self.learning_rate.powi((m - n) as i32));
-                }
-                sum1 = sum1 + sum2 * scores[n];
+    /// The L2 penalty on the square-table weights' distance from their starting values, to add to a batch's loss.
+    /// The material weights aren't stepped, so they are anchored already.
+    fn regularization(&self, tape: &'a Tape) -> Var<'a> {
+        let mut penalty = tape.var(0.0);
+        if self.options.regularization == 0.0 {
+            return penalty;
+        }
+        // Subtraction on the tape records the wrong derivative for its left operand, so add the negated anchor.
+        for (weight, &anchor) in self.weights.iter().zip(&self.anchor).skip(12) {
+            let drift = *weight + tape.var(-anchor);
+            penalty = penalty + drift * drift;
+        }
+        tape.var(self.options.regularization) * penalty
+    }
+
+    /// Zero any non-finite gradients, then scale the gradient down to at most the maximum norm.
+    fn clip_gradient(&self, grads: &mut [f64]) {
+        for (index, grad) in grads.iter_mut().enumerate() {
+            if !grad.is_finite() {
+                eprintln!("warning: ignoring non-finite gradient {} for weight {}", grad, index);
+                *grad = 0.0;
             }
+        }
 
-            print!("err: {} ", sum1.value());
-            println!();
+        // Normalise by the largest gradient first, so that squaring can't overflow.
+        let largest = grads.iter().fold(0.0_f64, |largest, grad| largest.max(grad.abs()));
+        if largest == 0.0 {
+            return;
+        }
+        let norm = largest * grads.iter().map(|grad| (grad / largest).powi(2)).sum::<f64>().sqrt();
 
-            let grad = sum1.grad();
+        if norm > self.options.max_grad_norm {
+            let scale = self.options.max_grad_norm / norm;
+            for grad in grads.iter_mut() {
+                *grad *= scale;
+            }
+        }
+    }
 
-            const BETA1: f64 = 0.9;
-            const BETA2: f64 = 0.999;
-            const EPSILON: f64 = 1e-8;
+    /// Apply one optimiser step for iteration `n` of an epoch.
+    fn step(&mut self, tape: &'a Tape, grads: &mut [f64], n: i32) {
+        self.clip_gradient(grads);
 
-            for (index, weight) in self.weights.iter_mut().enumerate().skip(12) {
-                if !grad.wrt(*weight).is_nan() {
-                    self.m_t[index] = BETA1.mul_add(self.m_t[index], (1.0 - BETA1)*grad.wrt(*weight));
-                    self.v_t[index] = BETA2.mul_add(self.v_t[index], (1.0 - BETA2)*grad.wrt(*weight)*grad.wrt(*weight));
+        let method = self.options.method;
+        for (index, weight) in self.weights.iter_mut().enumerate().skip(12) {
+            let value = method.update(weight.value(), grads[index], &mut self.m_t[index], &mut self.v_t[index], n);
+            *weight = tape.var(value);
+        }
+    }
+}
 
-                    let m_t = self.m_t[index] / (1.0 - BETA1.powi(n as i32));
-                    let v_t = self.v_t[index] / (1.0 - BETA2.powi(n as i32));
+#[cfg(test)]
+mod tests {
+    use super::{parse_labelled, random_opening, Tune, TuneMethod, TuneOptions};
+    use dorpsgek_movegen::{Board, Move};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tinyvec::ArrayVec;
+    use rand::Rng;
+    use revad::tape::Tape;
+
+    #[test]
+    fn seeded_tuners_agree() {
+        let tape = Tape::new();
+        let mut a = Tune::new_seeded(&tape, 42);
+        let mut b = Tune::new_seeded(&tape, 42);
+
+        let (a_weights, a_m_t, a_v_t) = a.get_state();
+        let (b_weights, b_m_t, b_v_t) = b.get_state();
+        assert_eq!(a_weights[..], b_weights[..]);
+        assert_eq!(a_m_t[..], b_m_t[..]);
+        assert_eq!(a_v_t[..], b_v_t[..]);
+
+        for _ in 0..16 {
+            assert_eq!(a.rng.gen::<u64>(), b.rng.gen::<u64>());
+        }
+    }
 
-                    *weight = tape.var(weight.value() - (100.0 * grad.wrt(*weight)) / (v_t.sqrt() + EPSILON) * m_t);
-                }
+    #[test]
+    fn pathological_gradients_stay_finite() {
+        let tape = Tape::new();
+        let mut tune = Tune::new_seeded(&tape, 0);
+        tune.set_options(TuneOptions { max_grad_norm: 0.5, ..TuneOptions::default() });
+
+        let mut grads = [0.0; 780];
+        grads[12] = f64::NAN;
+        grads[13] = f64::INFINITY;
+        grads[14] = f64::NEG_INFINITY;
+        grads[15] = 1e300;
+        grads[16] = -1e300;
+        grads[17] = 1e-300;
+
+        tune.step(&tape, &mut grads, 1);
+
+        let norm = grads.iter().map(|grad| grad * grad).sum::<f64>().sqrt();
+        assert!(norm <= 0.5 + 1e-9);
+        assert_eq!(grads[12], 0.0);
+        assert_eq!(grads[13], 0.0);
+        assert!(grads[15] > 0.0 && grads[16] < 0.0);
+
+        let (weights, m_t, v_t) = tune.get_state();
+        assert!(weights.iter().chain(&m_t).chain(&v_t).all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn adam_converges_faster_than_sgd() {
+        // Fit one weight to a target under a badly-scaled objective, 0.001 * (w - 100)^2.
+        let fit = |method: TuneMethod| {
+            let (mut weight, mut m_t, mut v_t) = (0.0, 0.0, 0.0);
+            for t in 1..=200 {
+                let tape = Tape::new();
+                let w = tape.var(weight);
+                let error = w + tape.var(-100.0);
+                let loss = tape.var(0.001) * error * error;
+                let grad = loss.grad().wrt(w);
+                weight = method.update(weight, grad, &mut m_t, &mut v_t, t);
             }
+            (weight - 100.0_f64).abs()
+        };
+
+        let sgd = fit(TuneMethod::Sgd { lr: 1.0 });
+        let adam = fit(TuneMethod::default());
+        assert!(adam < 5.0);
+        assert!(adam < sgd);
+    }
+
+    #[test]
+    fn epoch_reduces_error() {
+        let boards = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ].iter().map(|fen| Board::from_fen(fen).unwrap()).collect::<Vec<_>>();
+
+        let tape = Tape::new();
+        let mut tune = Tune::new_seeded(&tape, 1);
+        // The first few steps can overshoot, so give the error a few batches to come down.
+        tune.set_options(TuneOptions { batch_size: boards.len(), batches: 8, random_plies: 0, ..TuneOptions::default() });
+
+        let before = tune.mean_error(&tape, &boards);
+        tune.tune(&tape, &boards, 0);
+        let after = tune.mean_error(&tape, &boards);
+        assert!(after < before);
+    }
+
+    #[test]
+    fn texel_tuning_reduces_error() {
+        // A centralised knight wins and a cornered one loses, whichever side has it.
+        let positions = [
+            "4k3/8/8/8/3N4/8/8/4K3 w - - [1.0]",
+            "4k3/8/8/8/8/8/8/N3K3 w - - [0.0]",
+            "4k3/8/8/3n4/8/8/8/4K3 b - - [0.0]",
+            "n3k3/8/8/8/8/8/8/4K3 b - - [1.0]",
+            "4k3/8/8/8/8/8/8/4K3 w - - [0.5]",
+        ].iter().map(|line| parse_labelled(line).unwrap()).collect::<Vec<_>>();
+
+        let tape = Tape::new();
+        let mut tune = Tune::new_seeded(&tape, 2);
+        tune.set_options(TuneOptions { batch_size: positions.len(), batches: 8, ..TuneOptions::default() });
+
+        let mut error = tune.texel_error(&tape, &positions, 200.0);
+        for epoch in 0..3 {
+            tune.tune_texel(&tape, &positions, 200.0, epoch);
+            let new_error = tune.texel_error(&tape, &positions, 200.0);
+            assert!(new_error < error, "{} >= {}", new_error, error);
+            error = new_error;
         }
+
+        assert!(parse_labelled("4k3/8/8/8/8/8/8/4K3 w - -").is_none());
+        assert!(parse_labelled("4k3/8/8/8/8/8/8/4K3 w - - [2.0]").is_none());
+    }
+
+    #[test]
+    fn regularization_bounds_weight_drift() {
+        let positions = [
+            "4k3/8/8/8/3N4/8/8/4K3 w - - [1.0]",
+            "4k3/8/8/8/8/8/8/N3K3 w - - [0.0]",
+            "4k3/8/8/3n4/8/8/8/4K3 b - - [0.0]",
+            "n3k3/8/8/8/8/8/8/4K3 b - - [1.0]",
+        ].iter().map(|line| parse_labelled(line).unwrap()).collect::<Vec<_>>();
+
+        // How far the weights end up from where they started after many steps.
+        let drift = |regularization| {
+            let tape = Tape::new();
+            let mut tune = Tune::new_seeded(&tape, 4);
+            let (start, _, _) = tune.get_state();
+            tune.set_options(TuneOptions { batch_size: positions.len(), batches: 200, regularization, ..TuneOptions::default() });
+            tune.tune_texel(&tape, &positions, 200.0, 0);
+            let (end, _, _) = tune.get_state();
+            start.iter().zip(&end).map(|(start, end)| (end - start).powi(2)).sum::<f64>().sqrt()
+        };
+
+        let free = drift(0.0);
+        let anchored = drift(0.01);
+        assert!(anchored < free / 2.0, "{} >= {} / 2", anchored, free);
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let tape = Tape::new();
+        let mut tune = Tune::new_seeded(&tape, 3);
+
+        let weights = (0..780).map(f64::from).collect::<Vec<_>>();
+        let m_t = (0..780).map(|i| f64::from(i) * 0.5).collect::<Vec<_>>();
+        let v_t = (0..780).map(|i| f64::from(i) * 0.25).collect::<Vec<_>>();
+        tune.set_state(&tape, &weights, &m_t, &v_t);
+
+        let (new_weights, new_m_t, new_v_t) = tune.get_state();
+        assert_eq!(new_weights[..], weights[..]);
+        assert_eq!(new_m_t[..], m_t[..]);
+        assert_eq!(new_v_t[..], v_t[..]);
+    }
+
+    #[test]
+    fn random_openings_are_playable() {
+        let startpos = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+
+        for _ in 0..200 {
+            let board = random_opening(&startpos, 8, &mut rng);
+            assert!(board.validate().is_ok());
+
+            let mut moves = ArrayVec::from([Move::default(); 256]);
+            moves.set_len(0);
+            board.generate(&mut moves);
+            assert!(!moves.is_empty());
+        }
+
+        // White is already mated, so every line is rejected.
+        let mated = Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert!(random_opening(&mated, 1, &mut rng).hash() == mated.hash());
     }
 }